@@ -0,0 +1,412 @@
+use std::fmt::Display;
+
+use crate::{
+    global_pixel::{GlobalPixel, GlobalPixelError},
+    map_coords::MapCoords,
+    tile_coords::TileCoords,
+    tile_downloader::Tile,
+};
+
+const TILE_SIZE: u32 = GlobalPixel::TILE_SIZE;
+const CANVAS_SIZE: u32 = GlobalPixel::CANVAS_SIZE;
+
+/// A rectangle of the canvas, at least 1x1 and at most as wide as the canvas
+///
+/// Like the map, rectangles go around the antimeridian: one going past the right edge of the
+/// canvas carries on from its left edge. They never go past the top or bottom edges
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CanvasRect {
+    pub(crate) top_left_corner: GlobalPixel,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum CanvasRectError {
+    #[error("Width is 0")]
+    InvalidWidth,
+    #[error("Height is 0")]
+    InvalidHeight,
+    #[error("GlobalPixel Error: {0}")]
+    GlobalPixelError(#[from] GlobalPixelError),
+}
+
+impl CanvasRect {
+    pub fn new(
+        top_left_corner: GlobalPixel,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, CanvasRectError> {
+        if width == 0 {
+            return Err(CanvasRectError::InvalidWidth);
+        }
+        if height == 0 {
+            return Err(CanvasRectError::InvalidHeight);
+        }
+
+        top_left_corner.get_area_end(width, height)?;
+        Ok(Self {
+            top_left_corner,
+            width,
+            height,
+        })
+    }
+
+    pub fn from_tile_coords(
+        top_left_corner: &TileCoords,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, CanvasRectError> {
+        Self::new(GlobalPixel::try_from(top_left_corner)?, width, height)
+    }
+
+    /// The smallest rectangle including both pixels, which can be any two opposite corners
+    ///
+    /// It never goes around the antimeridian
+    pub fn from_corners(corner: GlobalPixel, opposite_corner: GlobalPixel) -> Self {
+        Self {
+            top_left_corner: GlobalPixel {
+                x: corner.x.min(opposite_corner.x),
+                y: corner.y.min(opposite_corner.y),
+            },
+            width: corner.x.abs_diff(opposite_corner.x) + 1,
+            height: corner.y.abs_diff(opposite_corner.y) + 1,
+        }
+    }
+
+    /// The whole 1000x1000 tile, `None` for tiles outside of the canvas
+    pub fn from_tile(tile: Tile) -> Option<Self> {
+        let top_left_corner =
+            GlobalPixel::new(tile.x as u32 * TILE_SIZE, tile.y as u32 * TILE_SIZE).ok()?;
+        Self::new(top_left_corner, TILE_SIZE, TILE_SIZE).ok()
+    }
+
+    pub fn get_top_left_corner(&self) -> GlobalPixel {
+        self.top_left_corner
+    }
+
+    /// Included in the rectangle, left of the top-left corner when it goes around the
+    /// antimeridian
+    pub fn get_bottom_right_corner(&self) -> GlobalPixel {
+        GlobalPixel {
+            x: (self.top_left_corner.x + self.width - 1) % CANVAS_SIZE,
+            y: self.top_left_corner.y + self.height - 1,
+        }
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// How many pixels the rectangle covers
+    pub fn get_area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    /// Whether the rectangle goes past the right edge of the canvas, carrying on from its left
+    /// edge
+    pub fn is_wrapping(&self) -> bool {
+        self.top_left_corner.x + self.width > CANVAS_SIZE
+    }
+
+    /// How far right of the left edge of the rectangle `x` is, going around the antimeridian
+    fn get_x_offset(&self, x: u32) -> u32 {
+        (x + CANVAS_SIZE - self.top_left_corner.x) % CANVAS_SIZE
+    }
+
+    /// Rows covered by the rectangle, the end is excluded
+    fn get_rows(&self) -> std::ops::Range<u32> {
+        self.top_left_corner.y..self.top_left_corner.y + self.height
+    }
+
+    pub fn contains_pixel(&self, pixel: GlobalPixel) -> bool {
+        self.get_x_offset(pixel.x) < self.width && self.get_rows().contains(&pixel.y)
+    }
+
+    /// Whether every column of `other` is in this rectangle
+    fn contains_columns(&self, other: &CanvasRect) -> bool {
+        self.width == CANVAS_SIZE
+            || self.get_x_offset(other.top_left_corner.x) + other.width <= self.width
+    }
+
+    /// Whether every pixel of `other` is in this rectangle
+    pub fn contains(&self, other: &CanvasRect) -> bool {
+        let rows = self.get_rows();
+        let other_rows = other.get_rows();
+        self.contains_columns(other) && rows.start <= other_rows.start && other_rows.end <= rows.end
+    }
+
+    pub fn intersects(&self, other: &CanvasRect) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// The pixels both rectangles cover, `None` when they don't overlap
+    ///
+    /// Rectangles wide enough to overlap on both sides of the antimeridian give the overlap
+    /// starting at the left edge of `self`
+    pub fn intersection(&self, other: &CanvasRect) -> Option<Self> {
+        let top = self.top_left_corner.y.max(other.top_left_corner.y);
+        let bottom = self.get_rows().end.min(other.get_rows().end);
+        if top >= bottom {
+            return None;
+        }
+
+        let self_in_other = other.get_x_offset(self.top_left_corner.x);
+        let other_in_self = self.get_x_offset(other.top_left_corner.x);
+        let (left, width) = if self.contains_columns(other) {
+            (other.top_left_corner.x, other.width)
+        } else if other.contains_columns(self) {
+            (self.top_left_corner.x, self.width)
+        } else if self_in_other < other.width {
+            (
+                self.top_left_corner.x,
+                self.width.min(other.width - self_in_other),
+            )
+        } else if other_in_self < self.width {
+            (
+                other.top_left_corner.x,
+                other.width.min(self.width - other_in_self),
+            )
+        } else {
+            return None;
+        };
+
+        Some(Self {
+            top_left_corner: GlobalPixel { x: left, y: top },
+            width,
+            height: bottom - top,
+        })
+    }
+
+    /// The smallest rectangle covering both, i.e. their bounding box, going around the
+    /// antimeridian when that's narrower
+    pub fn union(&self, other: &CanvasRect) -> Self {
+        let top = self.top_left_corner.y.min(other.top_left_corner.y);
+        let bottom = self.get_rows().end.max(other.get_rows().end);
+
+        let from_self = self
+            .width
+            .max(self.get_x_offset(other.top_left_corner.x) + other.width);
+        let from_other = other
+            .width
+            .max(other.get_x_offset(self.top_left_corner.x) + self.width);
+        let (left, width) = match from_self <= from_other {
+            true => (self.top_left_corner.x, from_self),
+            false => (other.top_left_corner.x, from_other),
+        };
+
+        Self {
+            top_left_corner: GlobalPixel { x: left, y: top },
+            width: width.min(CANVAS_SIZE),
+            height: bottom - top,
+        }
+    }
+
+    /// Grows the rectangle by `margin` pixels on every side, going around the antimeridian and
+    /// stopping at the top and bottom edges of the canvas
+    pub fn expand(&self, margin: u32) -> Self {
+        let top = self.top_left_corner.y.saturating_sub(margin);
+        let bottom = self.get_rows().end.saturating_add(margin).min(CANVAS_SIZE);
+
+        Self {
+            top_left_corner: GlobalPixel {
+                x: self.top_left_corner.wrapping_sub(margin, 0).x,
+                y: top,
+            },
+            width: (self.width as u64 + 2 * margin as u64).min(CANVAS_SIZE as u64) as u32,
+            height: bottom - top,
+        }
+    }
+
+    /// The point halfway between the edges of the rectangle
+    pub fn get_center(&self) -> MapCoords {
+        MapCoords::from_canvas_position(
+            (self.top_left_corner.x as f64 + self.width as f64 / 2f64) % CANVAS_SIZE as f64,
+            self.top_left_corner.y as f64 + self.height as f64 / 2f64,
+            self.width,
+            self.height,
+        )
+    }
+
+    /// Where the outer edges of the rectangle are, in pixels from the top-left corner of the
+    /// canvas: left, top, right and bottom
+    ///
+    /// The right edge goes past the right edge of the canvas for rectangles going around the
+    /// antimeridian
+    fn get_edges(&self) -> (f64, f64, f64, f64) {
+        (
+            self.top_left_corner.x as f64,
+            self.top_left_corner.y as f64,
+            (self.top_left_corner.x + self.width) as f64,
+            self.get_rows().end as f64,
+        )
+    }
+
+    /// Where the outer corners of the rectangle are: top-left, top-right, bottom-right and
+    /// bottom-left
+    ///
+    /// The right corners are past 180° of longitude for rectangles going around the
+    /// antimeridian, like with the `geo_types::Rect` conversion, so they stay east of the left
+    /// ones
+    pub fn get_corners(&self) -> [MapCoords; 4] {
+        let (left, top, right, bottom) = self.get_edges();
+
+        [(left, top), (right, top), (right, bottom), (left, bottom)]
+            .map(|(x, y)| MapCoords::from_canvas_position(x, y, self.width, self.height))
+    }
+
+    /// The outline of the rectangle on the map as a closed ring, counterclockwise from the
+    /// top-left corner like GeoJSON wants it
+    ///
+    /// Longitudes go past 180° like in [`Self::get_corners`]
+    pub fn get_footprint(&self) -> Vec<MapCoords> {
+        let [top_left, top_right, bottom_right, bottom_left] = self.get_corners();
+        vec![
+            top_left.clone(),
+            bottom_left,
+            bottom_right,
+            top_right,
+            top_left,
+        ]
+    }
+
+    /// Ground area covered by the rectangle
+    ///
+    /// Integrates Mercator's scale factor over the rows, so rectangles spanning many latitudes
+    /// are measured exactly
+    pub fn get_area_km2(&self) -> f64 {
+        let [top_left, _, _, bottom_left] = self.get_corners();
+        let longitudes = self.width as f64 / CANVAS_SIZE as f64 * 2f64 * std::f64::consts::PI;
+        let latitudes =
+            top_left.get_lat().to_radians().sin() - bottom_left.get_lat().to_radians().sin();
+        MapCoords::EARTH_RADIUS.powi(2) * longitudes * latitudes / 1_000_000f64
+    }
+
+    /// Ground size of the side of a pixel at the center of the rectangle, see
+    /// [`MapCoords::get_meters_per_pixel`]
+    pub fn get_meters_per_pixel(&self) -> f64 {
+        self.get_center().get_meters_per_pixel()
+    }
+
+    /// Every tile the rectangle touches, row by row
+    pub fn get_tiles(&self) -> impl Iterator<Item = Tile> + use<> {
+        let columns = self
+            .top_left_corner
+            .get_tile_columns(self.width)
+            .take(GlobalPixel::CANVAS_TILES as usize)
+            .collect::<Vec<_>>();
+        itertools::iproduct!(
+            self.top_left_corner.get_tile().y..=self.get_bottom_right_corner().get_tile().y,
+            columns
+        )
+        .map(|(tile_y, tile_x)| Tile::new(tile_x, tile_y))
+    }
+
+    /// Every tile the rectangle touches along with the part of the rectangle inside of it,
+    /// row by row
+    ///
+    /// Rectangles as wide as the canvas not starting on a tile edge touch their first column
+    /// of tiles on both sides, giving two parts for each of those tiles
+    pub fn get_tile_rects(&self) -> impl Iterator<Item = (Tile, CanvasRect)> + use<> {
+        let rect = *self;
+        let first_tile = self.top_left_corner.get_tile();
+        let columns = self.top_left_corner.get_tile_columns(self.width).count() as u32;
+
+        itertools::iproduct!(
+            first_tile.y..=self.get_bottom_right_corner().get_tile().y,
+            0..columns
+        )
+        .map(move |(tile_y, column)| {
+            // Past the right edge of the canvas instead of going around it
+            let tile_left = (first_tile.x as u32 + column) * TILE_SIZE;
+            let left = tile_left.max(rect.top_left_corner.x);
+            let right = (tile_left + TILE_SIZE).min(rect.top_left_corner.x + rect.width);
+
+            let tile_top = tile_y as u32 * TILE_SIZE;
+            let top = tile_top.max(rect.top_left_corner.y);
+            let bottom = (tile_top + TILE_SIZE).min(rect.get_rows().end);
+
+            (
+                Tile::new(
+                    ((tile_left / TILE_SIZE) % GlobalPixel::CANVAS_TILES) as u16,
+                    tile_y,
+                ),
+                CanvasRect {
+                    top_left_corner: GlobalPixel {
+                        x: left % CANVAS_SIZE,
+                        y: top,
+                    },
+                    width: right - left,
+                    height: bottom - top,
+                },
+            )
+        })
+    }
+}
+
+/// In longitude and latitude, longitudes go past 180 for rectangles going around the
+/// antimeridian so the rect keeps its width
+impl From<CanvasRect> for geo_types::Rect<f64> {
+    fn from(value: CanvasRect) -> Self {
+        let (left, top, right, bottom) = value.get_edges();
+        let top_left = MapCoords::from_canvas_position(left, top, value.width, value.height);
+        let bottom_right =
+            MapCoords::from_canvas_position(right, bottom, value.width, value.height);
+        geo_types::Rect::new(
+            geo_types::coord! { x: top_left.get_lng(), y: top_left.get_lat() },
+            geo_types::coord! { x: bottom_right.get_lng(), y: bottom_right.get_lat() },
+        )
+    }
+}
+
+/// The footprint of the rectangle, see [`geo_types::Rect`]'s conversion
+impl From<CanvasRect> for geo_types::Polygon<f64> {
+    fn from(value: CanvasRect) -> Self {
+        geo_types::Rect::from(value).to_polygon()
+    }
+}
+
+/// Every pixel the rect covers, even partially
+///
+/// Longitudes past ±180 go around the antimeridian
+impl TryFrom<geo_types::Rect<f64>> for CanvasRect {
+    type Error = CanvasRectError;
+
+    fn try_from(value: geo_types::Rect<f64>) -> Result<Self, Self::Error> {
+        let (min, max) = (value.min(), value.max());
+        let (left, top) = MapCoords::new(max.y, min.x, 0.0).get_canvas_position();
+        let (right, bottom) = MapCoords::new(min.y, max.x, 0.0).get_canvas_position();
+
+        let top_left_corner = GlobalPixel::from_canvas_position(left, top).ok_or(
+            GlobalPixelError::MapCoordsOutOfCanvas {
+                lat: max.y,
+                lng: min.x,
+            },
+        )?;
+        // Edges a hair past a pixel edge because of floating point math don't reach into the
+        // next pixel
+        let size = |start: u32, end: f64| ((end - 1e-6).ceil() - start as f64).max(1f64) as u32;
+        // The left edge went around the antimeridian as many times as the right one has to
+        let wraps = ((left - top_left_corner.x as f64) / CANVAS_SIZE as f64).round();
+
+        Self::new(
+            top_left_corner,
+            size(top_left_corner.x, right - wraps * CANVAS_SIZE as f64),
+            size(top_left_corner.y, bottom),
+        )
+    }
+}
+
+impl Display for CanvasRect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{} at {}",
+            self.width, self.height, self.top_left_corner
+        )
+    }
+}
@@ -1,6 +1,9 @@
 use std::fmt::Display;
 
-#[derive(Hash, Eq, PartialEq, Copy, Clone, PartialOrd, Ord)]
+/// Serialized as the name of the variant, e.g. `"DarkGray"`
+#[derive(
+    Debug, Hash, Eq, PartialEq, Copy, Clone, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
 pub enum Color {
     Black,
     DarkGray,
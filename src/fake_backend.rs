@@ -0,0 +1,461 @@
+//! A local stand-in for the wplace backend and Nominatim, to test against without the network
+//!
+//! Only available with the `test-support` feature
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use image::RgbaImage;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::{
+    color::Color, global_pixel::GlobalPixel, rate_limiter::RateLimiter, retry_policy::RetryPolicy,
+    tile_coords::TileCoords, tile_downloader::Tile, wplace_client::WplaceClientBuilder,
+};
+
+/// The biggest request head that is read, bodies are ignored
+const MAX_REQUEST_SIZE: usize = 16 * 1024;
+
+/// A response queued up with [`FakeBackend::push_response`], served instead of the normal one
+#[derive(Clone, Debug)]
+pub enum FakeResponse {
+    /// Any status along with its body
+    Status { status: u32, body: String },
+    /// `429 Too Many Requests`, with a `Retry-After` in whole seconds when set
+    TooManyRequests { retry_after: Option<u64> },
+    /// Waits before serving the normal response
+    Delay(Duration),
+    /// Closes the connection without answering
+    Disconnect,
+}
+
+#[derive(Default)]
+struct FakeBackendState {
+    /// Encoded PNGs, along with their ETag
+    tiles: HashMap<Tile, (Vec<u8>, String)>,
+    /// Served for every tile that wasn't inserted, `None` answers `404`
+    default_tile: Option<(Vec<u8>, String)>,
+    /// Raw JSON bodies
+    pixel_info: HashMap<TileCoords, String>,
+    /// Raw JSON body, `None` answers with an area around the requested point
+    nominatim_response: Option<String>,
+    nominatim_display_name: String,
+    /// Responses queued by path prefix
+    scripted: Vec<(String, FakeResponse)>,
+    latency: Duration,
+    /// Path and query of every request, oldest first
+    requests: Vec<String>,
+}
+
+/// An HTTP server on `127.0.0.1` answering like the wplace backend and Nominatim
+///
+/// Tiles are served from `/files/{season}/tiles/{x}/{y}.png`, pixel info from
+/// `/{season}/pixel/{x}/{y}` and reverse geocoding from `/reverse`
+///
+/// The server stops when dropped
+pub struct FakeBackend {
+    address: SocketAddr,
+    state: Arc<Mutex<FakeBackendState>>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for FakeBackend {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct FakeRequest {
+    /// Path and query
+    target: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: Vec<(String, String)>,
+}
+
+impl FakeRequest {
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn encode_png(image: &RgbaImage) -> Vec<u8> {
+    let mut data = std::io::Cursor::new(Vec::new());
+    // Writing to memory can't fail
+    image
+        .write_to(&mut data, image::ImageFormat::Png)
+        .expect("Encoding a PNG to memory failed");
+    data.into_inner()
+}
+
+/// The tile along with an ETag derived from its content
+fn with_etag(data: Vec<u8>) -> (Vec<u8>, String) {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    data.hash(&mut hasher);
+    let etag = format!("\"{:016x}\"", hasher.finish());
+    (data, etag)
+}
+
+fn solid_tile(color: Color) -> RgbaImage {
+    RgbaImage::from_pixel(
+        GlobalPixel::TILE_SIZE,
+        GlobalPixel::TILE_SIZE,
+        image::Rgba(color.into()),
+    )
+}
+
+fn parse_request(head: &str) -> Option<FakeRequest> {
+    let mut lines = head.split("\r\n");
+    let target = lines.next()?.split(' ').nth(1)?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    Some(FakeRequest {
+        target: target.to_string(),
+        path: path.to_string(),
+        query: query
+            .split('&')
+            .filter_map(|v| v.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        headers: lines
+            .filter_map(|v| v.split_once(':'))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect(),
+    })
+}
+
+async fn write_response(
+    stream: &mut TcpStream,
+    status: u32,
+    headers: &[(&str, String)],
+    body: &[u8],
+) -> std::io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {status} Fake\r\nContent-Length: {}\r\nConnection: close\r\n",
+        body.len()
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.shutdown().await
+}
+
+impl FakeBackend {
+    /// Binds a random port and starts serving
+    pub async fn start() -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(FakeBackendState {
+            nominatim_display_name: String::from("Fake Place"),
+            ..Default::default()
+        }));
+
+        let task = tokio::spawn({
+            let state = state.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(Self::handle(state.clone(), stream));
+                }
+            }
+        });
+
+        Ok(Self {
+            address,
+            state,
+            task,
+        })
+    }
+
+    /// e.g. `http://127.0.0.1:12345`
+    pub fn get_url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// A client builder pointing every endpoint at this server, without rate limits and with
+    /// short retry backoffs
+    pub fn client_builder(&self) -> WplaceClientBuilder {
+        WplaceClientBuilder::default()
+            .tile_base_url(format!("{}/files", self.get_url()))
+            .backend_url(self.get_url())
+            .nominatim_endpoint(format!("{}/reverse", self.get_url()))
+            .rate_limiter(Arc::new(RateLimiter::new()))
+            .retry_policy(
+                RetryPolicy::default()
+                    .with_initial_backoff(Duration::from_millis(10))
+                    .with_max_backoff(Duration::from_millis(100)),
+            )
+    }
+
+    pub fn insert_tile(&self, tile: Tile, image: &RgbaImage) {
+        self.insert_tile_png(tile, encode_png(image));
+    }
+
+    /// Served as is, which makes it possible to serve broken tiles
+    pub fn insert_tile_png(&self, tile: Tile, data: Vec<u8>) {
+        self.state
+            .lock()
+            .unwrap()
+            .tiles
+            .insert(tile, with_etag(data));
+    }
+
+    /// A tile painted all over with `color`
+    pub fn fill_tile(&self, tile: Tile, color: Color) {
+        self.insert_tile(tile, &solid_tile(color));
+    }
+
+    pub fn remove_tile(&self, tile: Tile) {
+        self.state.lock().unwrap().tiles.remove(&tile);
+    }
+
+    /// What tiles that weren't inserted look like, `None` answers them with `404`
+    pub fn set_default_tile(&self, color: Option<Color>) {
+        self.state.lock().unwrap().default_tile =
+            color.map(|v| with_etag(encode_png(&solid_tile(v))));
+    }
+
+    /// By default, pixels are reported as never painted
+    pub fn set_pixel_info(
+        &self,
+        coords: TileCoords,
+        painter_id: u64,
+        painter_name: &str,
+        alliance: Option<(u64, &str)>,
+    ) {
+        let (alliance_id, alliance_name) = alliance.unwrap_or((0, ""));
+        let body = serde_json::json!({
+            "paintedBy": {
+                "id": painter_id,
+                "name": painter_name,
+                "allianceId": alliance_id,
+                "allianceName": alliance_name,
+            },
+        });
+        self.state
+            .lock()
+            .unwrap()
+            .pixel_info
+            .insert(coords, body.to_string());
+    }
+
+    /// By default every lookup answers with a small area called `display_name` around the
+    /// requested point
+    pub fn set_nominatim_display_name(&self, display_name: impl ToString) {
+        self.state.lock().unwrap().nominatim_display_name = display_name.to_string();
+    }
+
+    /// Answers every lookup with `body`, e.g. `{"error":"Unable to geocode"}`
+    pub fn set_nominatim_response(&self, body: Option<String>) {
+        self.state.lock().unwrap().nominatim_response = body;
+    }
+
+    /// Serves `response` to the next request whose path starts with `path_prefix`
+    ///
+    /// Responses are queued, the oldest matching one is served first
+    pub fn push_response(&self, path_prefix: impl ToString, response: FakeResponse) {
+        self.state
+            .lock()
+            .unwrap()
+            .scripted
+            .push((path_prefix.to_string(), response));
+    }
+
+    /// Added to every response
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Path and query of every request, oldest first
+    pub fn get_requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// How many requests had a path starting with `path_prefix`
+    pub fn get_request_count(&self, path_prefix: &str) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|v| v.starts_with(path_prefix))
+            .count()
+    }
+
+    async fn handle(state: Arc<Mutex<FakeBackendState>>, mut stream: TcpStream) {
+        let mut head = Vec::new();
+        let mut buffer = [0; 1024];
+        while !head.ends_with(b"\r\n\r\n") && head.len() < MAX_REQUEST_SIZE {
+            match stream.read(&mut buffer).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => head.extend_from_slice(&buffer[..n]),
+            }
+        }
+        let Some(request) = parse_request(&String::from_utf8_lossy(&head)) else {
+            return;
+        };
+
+        let (latency, scripted) = {
+            let mut state = state.lock().unwrap();
+            state.requests.push(request.target.clone());
+
+            let mut scripted = Vec::new();
+            while let Some(i) = state
+                .scripted
+                .iter()
+                .position(|(prefix, _)| request.path.starts_with(prefix.as_str()))
+            {
+                let (_, response) = state.scripted.remove(i);
+                let is_delay = matches!(response, FakeResponse::Delay(_));
+                scripted.push(response);
+                // Delays are followed by whatever comes next
+                if !is_delay {
+                    break;
+                }
+            }
+            (state.latency, scripted)
+        };
+
+        tokio::time::sleep(latency).await;
+
+        for response in scripted {
+            let result = match response {
+                FakeResponse::Delay(delay) => {
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                FakeResponse::Status { status, body } => {
+                    write_response(&mut stream, status, &[], body.as_bytes()).await
+                }
+                FakeResponse::TooManyRequests { retry_after } => {
+                    let headers = retry_after
+                        .map(|v| vec![("Retry-After", v.to_string())])
+                        .unwrap_or_default();
+                    write_response(&mut stream, 429, &headers, b"Too Many Requests").await
+                }
+                FakeResponse::Disconnect => Ok(()),
+            };
+            // The client going away isn't the server's problem
+            let _ = result;
+            return;
+        }
+
+        let _ = Self::respond(&state, &request, &mut stream).await;
+    }
+
+    async fn respond(
+        state: &Mutex<FakeBackendState>,
+        request: &FakeRequest,
+        stream: &mut TcpStream,
+    ) -> std::io::Result<()> {
+        let segments = request
+            .path
+            .trim_matches('/')
+            .split('/')
+            .collect::<Vec<_>>();
+
+        match segments.as_slice() {
+            ["files", _, "tiles", x, y] => {
+                let tile = x
+                    .parse()
+                    .ok()
+                    .zip(y.strip_suffix(".png").and_then(|v| v.parse().ok()))
+                    .map(|(x, y)| Tile::new(x, y));
+                let found = tile.and_then(|tile| {
+                    let state = state.lock().unwrap();
+                    state
+                        .tiles
+                        .get(&tile)
+                        .or(state.default_tile.as_ref())
+                        .cloned()
+                });
+
+                match found {
+                    None => write_response(stream, 404, &[], b"Not Found").await,
+                    Some((_, etag)) if request.get_header("If-None-Match") == Some(&etag) => {
+                        write_response(stream, 304, &[("ETag", etag)], b"").await
+                    }
+                    Some((data, etag)) => {
+                        let headers = [("Content-Type", String::from("image/png")), ("ETag", etag)];
+                        write_response(stream, 200, &headers, &data).await
+                    }
+                }
+            }
+            [_, "pixel", tile_x, tile_y] => {
+                let coords = (|| {
+                    Some(TileCoords::new(
+                        tile_x.parse().ok()?,
+                        tile_y.parse().ok()?,
+                        request.query.get("x")?.parse().ok()?,
+                        request.query.get("y")?.parse().ok()?,
+                    ))
+                })();
+                let Some(coords) = coords else {
+                    return write_response(stream, 400, &[], b"Bad Request").await;
+                };
+
+                let body = state
+                    .lock()
+                    .unwrap()
+                    .pixel_info
+                    .get(&coords)
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        String::from(
+                            r#"{"paintedBy":{"id":0,"name":"","allianceId":0,"allianceName":""}}"#,
+                        )
+                    });
+                write_response(stream, 200, &[], body.as_bytes()).await
+            }
+            ["reverse"] => {
+                let (response, display_name) = {
+                    let state = state.lock().unwrap();
+                    (
+                        state.nominatim_response.clone(),
+                        state.nominatim_display_name.clone(),
+                    )
+                };
+                let body = response.unwrap_or_else(|| {
+                    let lat: f64 = request
+                        .query
+                        .get("lat")
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or_default();
+                    let lng: f64 = request
+                        .query
+                        .get("lon")
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or_default();
+                    let ring = [(0.0, -0.01), (0.01, 0.0), (0.0, 0.01), (-0.01, 0.0)]
+                        .iter()
+                        .chain(std::iter::once(&(0.0, -0.01)))
+                        .map(|(d_lng, d_lat)| [lng + d_lng, lat + d_lat])
+                        .collect::<Vec<_>>();
+                    serde_json::json!({
+                        "display_name": display_name,
+                        "addresstype": "city",
+                        "geojson": { "type": "Polygon", "coordinates": [ring] },
+                    })
+                    .to_string()
+                });
+                write_response(stream, 200, &[], body.as_bytes()).await
+            }
+            _ => write_response(stream, 404, &[], b"Not Found").await,
+        }
+    }
+}
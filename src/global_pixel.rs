@@ -0,0 +1,252 @@
+use std::fmt::Display;
+
+use crate::{
+    map_coords::MapCoords, tile_coords::TileCoords, tile_downloader::Tile,
+    web_mercator::WebMercatorCoords,
+};
+
+/// A pixel of the canvas, counted from its top-left corner across tiles
+///
+/// Always within the 2,048,000x2,048,000 canvas
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GlobalPixel {
+    pub(crate) x: u32,
+    pub(crate) y: u32,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum GlobalPixelError {
+    #[error("Pixel {x}, {y} is outside of the canvas")]
+    OutOfCanvas { x: u64, y: u64 },
+    #[error("Area of {width}x{height} at {x}, {y} goes past the canvas")]
+    AreaOutOfCanvas {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    #[error("Tile pixel {x}, {y} is outside of its tile")]
+    OutOfTile { x: u16, y: u16 },
+    #[error("{lat}, {lng} isn't on the canvas")]
+    MapCoordsOutOfCanvas { lat: f64, lng: f64 },
+    #[error("EPSG:3857 point {x}, {y} isn't on the canvas")]
+    WebMercatorOutOfCanvas { x: f64, y: f64 },
+}
+
+impl GlobalPixel {
+    /// Pixels per tile side
+    pub const TILE_SIZE: u32 = 1000;
+    /// Tiles per canvas side
+    pub const CANVAS_TILES: u32 = 2048;
+    /// Pixels per canvas side
+    pub const CANVAS_SIZE: u32 = Self::TILE_SIZE * Self::CANVAS_TILES;
+
+    pub fn new(x: u32, y: u32) -> Result<Self, GlobalPixelError> {
+        match x < Self::CANVAS_SIZE && y < Self::CANVAS_SIZE {
+            true => Ok(Self { x, y }),
+            false => Err(GlobalPixelError::OutOfCanvas {
+                x: x as u64,
+                y: y as u64,
+            }),
+        }
+    }
+
+    /// The pixel covering a position in pixels from the top-left corner of the canvas
+    ///
+    /// Pixels cover `[x, x + 1)`, positions a hair short of the next pixel are snapped to it,
+    /// so corners that went through floating point math still land on their pixel
+    ///
+    /// `x` goes around the antimeridian, `y` must be on the canvas
+    pub(crate) fn from_canvas_position(x: f64, y: f64) -> Option<Self> {
+        const SNAP: f64 = 1e-6;
+        let size = Self::CANVAS_SIZE as f64;
+        let x = (x + SNAP).floor().rem_euclid(size);
+        let y = match y > -SNAP {
+            true => (y + SNAP).floor().max(0.0),
+            false => return None,
+        };
+        match x.is_finite() && y < size {
+            true => Some(Self {
+                x: x as u32,
+                y: y as u32,
+            }),
+            false => None,
+        }
+    }
+
+    /// The pixel under a point, through the inverse of the projection
+    /// [`MapCoords::from_global_pixel`] uses
+    ///
+    /// The canvas only reaches about ±85.05° of latitude, the poles aren't on it. Longitudes go
+    /// around the antimeridian, 190° is the same as -170°
+    pub fn from_map_coords(map_coords: &MapCoords) -> Result<Self, GlobalPixelError> {
+        let (x, y) = map_coords.get_canvas_position();
+        Self::from_canvas_position(x, y).ok_or(GlobalPixelError::MapCoordsOutOfCanvas {
+            lat: map_coords.get_lat(),
+            lng: map_coords.get_lng(),
+        })
+    }
+
+    /// The pixel under a point in EPSG:3857 meters
+    ///
+    /// Points past the left or right edge of the canvas go around the antimeridian
+    pub fn from_web_mercator(coords: &WebMercatorCoords) -> Result<Self, GlobalPixelError> {
+        let (x, y) = coords.get_canvas_position();
+        Self::from_canvas_position(x, y).ok_or(GlobalPixelError::WebMercatorOutOfCanvas {
+            x: coords.get_x(),
+            y: coords.get_y(),
+        })
+    }
+
+    pub fn get_x(&self) -> u32 {
+        self.x
+    }
+
+    pub fn get_y(&self) -> u32 {
+        self.y
+    }
+
+    /// The tile this pixel is in
+    pub fn get_tile(&self) -> Tile {
+        Tile::new(
+            (self.x / Self::TILE_SIZE) as u16,
+            (self.y / Self::TILE_SIZE) as u16,
+        )
+    }
+
+    /// Where this pixel is within its tile
+    pub fn get_x_in_tile(&self) -> u16 {
+        (self.x % Self::TILE_SIZE) as u16
+    }
+
+    pub fn get_y_in_tile(&self) -> u16 {
+        (self.y % Self::TILE_SIZE) as u16
+    }
+
+    /// The bottom-right pixel of a `width`x`height` area with this pixel as its top-left one
+    ///
+    /// Areas go around the antimeridian, so they can end left of where they start, but they
+    /// can't be wider than the canvas. Empty areas end where they start
+    pub fn get_area_end(&self, width: u32, height: u32) -> Result<Self, GlobalPixelError> {
+        (width <= Self::CANVAS_SIZE)
+            .then(|| {
+                self.horizontal_wrapping_add(width.saturating_sub(1), height.saturating_sub(1))
+            })
+            .flatten()
+            .ok_or(GlobalPixelError::AreaOutOfCanvas {
+                x: self.x,
+                y: self.y,
+                width,
+                height,
+            })
+    }
+
+    /// The tile columns an area `width` pixels wide starting at this pixel touches, left to
+    /// right and going around the antimeridian
+    ///
+    /// Areas as wide as the canvas not starting on a tile edge touch their first column twice
+    pub(crate) fn get_tile_columns(&self, width: u32) -> impl Iterator<Item = u16> + use<> {
+        let first_column = self.x / Self::TILE_SIZE;
+        let columns = (self.x % Self::TILE_SIZE + width.max(1) - 1) / Self::TILE_SIZE + 1;
+        (0..columns).map(move |i| ((first_column + i) % Self::CANVAS_TILES) as u16)
+    }
+
+    /// `None` when the result leaves the canvas
+    pub fn checked_add(&self, x: u32, y: u32) -> Option<Self> {
+        Self::new(self.x.checked_add(x)?, self.y.checked_add(y)?).ok()
+    }
+
+    /// `None` when the result leaves the canvas
+    pub fn checked_sub(&self, x: u32, y: u32) -> Option<Self> {
+        Self::new(self.x.checked_sub(x)?, self.y.checked_sub(y)?).ok()
+    }
+
+    /// Stops at the right and bottom edges of the canvas
+    pub fn saturating_add(&self, x: u32, y: u32) -> Self {
+        Self {
+            x: self.x.saturating_add(x).min(Self::CANVAS_SIZE - 1),
+            y: self.y.saturating_add(y).min(Self::CANVAS_SIZE - 1),
+        }
+    }
+
+    /// Stops at the left and top edges of the canvas
+    pub fn saturating_sub(&self, x: u32, y: u32) -> Self {
+        Self {
+            x: self.x.saturating_sub(x),
+            y: self.y.saturating_sub(y),
+        }
+    }
+
+    /// Goes around the antimeridian like the map does, `None` past the bottom edge
+    pub fn horizontal_wrapping_add(&self, x: u32, y: u32) -> Option<Self> {
+        Self::new(self.wrapping_add(x, 0).x, self.y.checked_add(y)?).ok()
+    }
+
+    /// Goes around the antimeridian like the map does, `None` past the top edge
+    pub fn horizontal_wrapping_sub(&self, x: u32, y: u32) -> Option<Self> {
+        Self::new(self.wrapping_sub(x, 0).x, self.y.checked_sub(y)?).ok()
+    }
+
+    /// Goes around the canvas, leaving the right edge comes back from the left one
+    pub fn wrapping_add(&self, x: u32, y: u32) -> Self {
+        Self {
+            x: ((self.x as u64 + x as u64) % Self::CANVAS_SIZE as u64) as u32,
+            y: ((self.y as u64 + y as u64) % Self::CANVAS_SIZE as u64) as u32,
+        }
+    }
+
+    /// Goes around the canvas, leaving the left edge comes back from the right one
+    pub fn wrapping_sub(&self, x: u32, y: u32) -> Self {
+        let wrap = |v: u32, d: u32| {
+            let d = d % Self::CANVAS_SIZE;
+            match v >= d {
+                true => v - d,
+                false => Self::CANVAS_SIZE - (d - v),
+            }
+        };
+        Self {
+            x: wrap(self.x, x),
+            y: wrap(self.y, y),
+        }
+    }
+}
+
+impl TryFrom<TileCoords> for GlobalPixel {
+    type Error = GlobalPixelError;
+
+    /// Fails for pixels outside of their tile, or tiles outside of the canvas
+    fn try_from(value: TileCoords) -> Result<Self, Self::Error> {
+        if value.x as u32 >= Self::TILE_SIZE || value.y as u32 >= Self::TILE_SIZE {
+            return Err(GlobalPixelError::OutOfTile {
+                x: value.x,
+                y: value.y,
+            });
+        }
+
+        Self::new(
+            value.tile_x as u32 * Self::TILE_SIZE + value.x as u32,
+            value.tile_y as u32 * Self::TILE_SIZE + value.y as u32,
+        )
+    }
+}
+
+impl TryFrom<&TileCoords> for GlobalPixel {
+    type Error = GlobalPixelError;
+
+    fn try_from(value: &TileCoords) -> Result<Self, Self::Error> {
+        Self::try_from(*value)
+    }
+}
+
+impl From<GlobalPixel> for TileCoords {
+    fn from(value: GlobalPixel) -> Self {
+        let tile = value.get_tile();
+        TileCoords::new(tile.x, tile.y, value.get_x_in_tile(), value.get_y_in_tile())
+    }
+}
+
+impl Display for GlobalPixel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(X: {}, Y: {})", self.x, self.y)
+    }
+}
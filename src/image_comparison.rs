@@ -1,21 +1,92 @@
 use std::{collections::HashMap, rc::Rc};
 
-use image::{GenericImage, GenericImageView};
+use image::{GenericImage, GenericImageView, Rgba};
+use itertools::Itertools;
 
-use crate::{color::Color, convert_px_to_hours, image_data::ImageData};
+use crate::{
+    canvas_rect::{CanvasRect, CanvasRectError},
+    color::Color,
+    convert_px_to_hours,
+    global_pixel::{GlobalPixel, GlobalPixelError},
+    image_data::{ImageData, ImageDataError},
+    region_view::RegionView,
+    tile_coords::TileCoords,
+    tile_source::{TileSource, TileSourceError},
+};
 
+/// Serialized without the difference image, which is rebuilt out of the different pixels when
+/// deserializing:
+/// `{"width": 0, "height": 0, "different_px": [[0, 0]], "difference_color_count": {"Black": 1},
+/// "total_different_px": 1}`
+#[derive(serde::Deserialize)]
+#[serde(try_from = "ImageComparisonJson")]
 pub struct ImageComparison {
     difference_image: image::RgbaImage,
     different_px: Rc<[(u32, u32)]>,
     difference_color_count: HashMap<Color, u32>,
 }
 
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ImageComparisonJson<P = Vec<(u32, u32)>, C = HashMap<Color, u32>> {
+    width: u32,
+    height: u32,
+    different_px: P,
+    difference_color_count: C,
+    /// Only there for consumers, ignored when deserializing
+    #[serde(default)]
+    total_different_px: u32,
+}
+
+impl serde::Serialize for ImageComparison {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ImageComparisonJson {
+            width: self.difference_image.width(),
+            height: self.difference_image.height(),
+            different_px: &*self.different_px,
+            difference_color_count: &self.difference_color_count,
+            total_different_px: self.get_total_different_px(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl TryFrom<ImageComparisonJson> for ImageComparison {
+    type Error = String;
+
+    fn try_from(value: ImageComparisonJson) -> Result<Self, Self::Error> {
+        let mut difference_image = image::RgbaImage::new(value.width, value.height);
+        for &(x, y) in &value.different_px {
+            if x >= value.width || y >= value.height {
+                return Err(format!(
+                    "Different pixel {x}, {y} is outside of the {}x{} image",
+                    value.width, value.height
+                ));
+            }
+            difference_image.put_pixel(x, y, image::Rgba::from([255, 0, 255, 255]));
+        }
+
+        Ok(Self {
+            difference_image,
+            different_px: value.different_px.into(),
+            difference_color_count: value.difference_color_count,
+        })
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ImageComparisonError {
     #[error("Incongruent Heights")]
     IncongruentWidth,
     #[error("Incongruent Widths")]
     IncongruentHeight,
+    #[error("ImageData Error: {0}")]
+    ImageDataError(#[from] ImageDataError),
+    #[error("TileSource Error: {0}")]
+    TileSourceError(#[from] TileSourceError),
+    #[error("GlobalPixel Error: {0}")]
+    GlobalPixelError(#[from] GlobalPixelError),
+    #[error("CanvasRect Error: {0}")]
+    CanvasRectError(#[from] CanvasRectError),
 }
 
 impl ImageComparison {
@@ -33,6 +104,36 @@ impl ImageComparison {
             return Err(ImageComparisonError::IncongruentWidth);
         }
 
+        Ok(Self::compare_pixels(
+            template_image,
+            current_image.image.pixels().map(|(_, _, pixel)| pixel),
+        ))
+    }
+
+    /// Compares `template_image` with a region of the canvas without copying it out of the tiles
+    pub fn compare_with_region(
+        template_image: &ImageData,
+        current_region: &RegionView,
+    ) -> Result<Self, ImageComparisonError> {
+        if template_image.height != current_region.get_height() {
+            return Err(ImageComparisonError::IncongruentHeight);
+        }
+
+        if template_image.width != current_region.get_width() {
+            return Err(ImageComparisonError::IncongruentWidth);
+        }
+
+        Ok(Self::compare_pixels(
+            template_image,
+            current_region.iter_pixels().map(|(_, _, pixel)| pixel),
+        ))
+    }
+
+    /// `current_pixels` must be as big as `template_image`, row by row
+    fn compare_pixels<I: Iterator<Item = Rgba<u8>>>(
+        template_image: &ImageData,
+        current_pixels: I,
+    ) -> Self {
         let mut difference_color_count = HashMap::new();
 
         let mut difference_image =
@@ -41,14 +142,14 @@ impl ImageComparison {
 
         let mut different_px = Vec::new();
 
-        for (x, y, pixel) in template_image.image.pixels() {
+        for ((x, y, pixel), current_pixel) in template_image.image.pixels().zip(current_pixels) {
             let template_pixel = pixel.0;
             let [_, _, _, template_alpha] = template_pixel;
             if template_alpha != 255 {
                 continue;
             }
 
-            let current_pixel = unsafe { current_image.image.unsafe_get_pixel(x, y).0 };
+            let current_pixel = current_pixel.0;
 
             if template_pixel == current_pixel {
                 continue;
@@ -75,11 +176,26 @@ impl ImageComparison {
             }
         }
 
-        Ok(Self {
+        Self {
             difference_color_count,
             difference_image,
             different_px: different_px.into(),
-        })
+        }
+    }
+
+    /// Compares `template_image` with the area of the canvas under it, as seen by `source`
+    pub async fn compare_with_source<S: TileSource>(
+        template_image: &ImageData,
+        top_left_corner: &TileCoords,
+        source: &S,
+    ) -> Result<Self, ImageComparisonError> {
+        let rect = CanvasRect::new(
+            GlobalPixel::try_from(top_left_corner)?,
+            template_image.width,
+            template_image.height,
+        )?;
+        let current_region = RegionView::from_site_rect(source, &rect).await?;
+        Self::compare_with_region(template_image, &current_region)
     }
 
     pub fn get_difference_color_count(&self) -> &HashMap<Color, u32> {
@@ -94,6 +210,48 @@ impl ImageComparison {
         self.different_px.clone()
     }
 
+    /// Where the different pixels are on the canvas, for a template placed at `top_left_corner`
+    ///
+    /// Handy to look their painters up with [`crate::wplace_client::WplaceClient::get_pixels_info`]
+    pub fn get_different_coords(
+        &self,
+        top_left_corner: GlobalPixel,
+    ) -> impl Iterator<Item = TileCoords> + use<> {
+        let different_px = self.different_px.clone();
+
+        (0..different_px.len()).filter_map(move |i| {
+            let (x, y) = different_px[i];
+            top_left_corner
+                .horizontal_wrapping_add(x, y)
+                .map(TileCoords::from)
+        })
+    }
+
+    /// The smallest area of the canvas holding every different pixel, for a template placed at
+    /// `top_left_corner`
+    ///
+    /// `None` when nothing differs
+    pub fn get_difference_rect(&self, top_left_corner: GlobalPixel) -> Option<CanvasRect> {
+        let (min_x, max_x) = self
+            .different_px
+            .iter()
+            .map(|(x, _)| *x)
+            .minmax()
+            .into_option()?;
+        let (min_y, max_y) = self
+            .different_px
+            .iter()
+            .map(|(_, y)| *y)
+            .minmax()
+            .into_option()?;
+        CanvasRect::new(
+            top_left_corner.horizontal_wrapping_add(min_x, min_y)?,
+            max_x - min_x + 1,
+            max_y - min_y + 1,
+        )
+        .ok()
+    }
+
     pub fn get_total_different_px(&self) -> u32 {
         self.difference_color_count.values().sum()
     }
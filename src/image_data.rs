@@ -3,7 +3,14 @@ use std::{collections::HashMap, rc::Rc};
 use image::{DynamicImage, GenericImageView, ImageBuffer, ImageReader, Rgba};
 
 use crate::{
-    color::Color, convert_px_to_hours, tile_coords::TileCoords, tile_downloader::TileDownloader,
+    canvas_rect::{CanvasRect, CanvasRectError},
+    color::Color,
+    convert_px_to_hours,
+    global_pixel::GlobalPixelError,
+    region_view::RegionView,
+    tile_coords::TileCoords,
+    tile_downloader::Tile,
+    tile_source::{TileImage, TileSource, TileSourceError},
 };
 
 /// Currently only supports PNG
@@ -24,6 +31,12 @@ pub enum ImageDataError {
     ImageError(#[from] image::error::ImageError),
     #[error("I/O Error: {0}")]
     IoError(#[from] std::io::Error),
+    #[error("TileSource Error: {0}")]
+    TileSourceError(#[from] TileSourceError),
+    #[error("GlobalPixel Error: {0}")]
+    GlobalPixelError(#[from] GlobalPixelError),
+    #[error("CanvasRect Error: {0}")]
+    CanvasRectError(#[from] CanvasRectError),
     #[error("Width is 0")]
     InvalidWidth,
     #[error("Height is 0")]
@@ -106,61 +119,57 @@ impl ImageData {
         })
     }
 
-    pub fn from_site_coords(
+    /// Fetches every tile the area touches from `source`, then stitches them together
+    pub async fn from_site_coords<S: TileSource>(
+        source: &S,
         top_left_corner: &TileCoords,
         width: u16,
         height: u16,
     ) -> Result<Self, ImageDataError> {
-        let last_tile_x = top_left_corner.tile_x + ((top_left_corner.x + width) / 1000);
-        let last_tile_y = top_left_corner.tile_y + ((top_left_corner.y + height) / 1000);
-
-        let global_x_offset =
-            (top_left_corner.tile_x as usize) * 1000 + (top_left_corner.x as usize);
-        let global_y_offset =
-            (top_left_corner.tile_y as usize) * 1000 + (top_left_corner.y as usize);
-
-        let mut new_image = image::ImageBuffer::new(width as u32, height as u32);
-
-        for (tile_x, tile_y) in itertools::iproduct!(
-            top_left_corner.tile_x..=last_tile_x,
-            top_left_corner.tile_y..=last_tile_y
-        ) {
-            let tile = TileDownloader::download(tile_x, tile_y)?;
-
-            let initial_x_in_tile = match top_left_corner.tile_x == tile_x {
-                true => top_left_corner.x,
-                false => 0,
-            };
-
-            let initial_y_in_tile = match top_left_corner.tile_y == tile_y {
-                true => top_left_corner.y,
-                false => 0,
-            };
-
-            let final_x_in_tile = match last_tile_x == tile_x {
-                true => top_left_corner.x + width - 1000 * (last_tile_x - top_left_corner.tile_x),
-                false => 1000,
-            };
+        let rect = CanvasRect::from_tile_coords(top_left_corner, width as u32, height as u32)?;
+        Self::from_site_rect(source, &rect).await
+    }
 
-            let final_y_in_tile = match last_tile_y == tile_y {
-                true => top_left_corner.y + height - 1000 * (last_tile_y - top_left_corner.tile_y),
-                false => 1000,
-            };
+    /// Fetches every tile `rect` touches from `source`, then stitches them together
+    pub async fn from_site_rect<S: TileSource>(
+        source: &S,
+        rect: &CanvasRect,
+    ) -> Result<Self, ImageDataError> {
+        Self::new(RegionView::from_site_rect(source, rect).await?.to_image())
+    }
 
-            for (x_in_tile, y_in_tile) in itertools::iproduct!(
-                initial_x_in_tile..final_x_in_tile,
-                initial_y_in_tile..final_y_in_tile
-            ) {
-                let pixel = unsafe { tile.unsafe_get_pixel(x_in_tile as u32, y_in_tile as u32) };
-                let x_global_offset = (tile_x as usize) * 1000 + (x_in_tile as usize);
-                let y_global_offset = (tile_y as usize) * 1000 + (y_in_tile as usize);
-                let x_in_image = x_global_offset - global_x_offset;
-                let y_in_image = y_global_offset - global_y_offset;
-                new_image.put_pixel(x_in_image as u32, y_in_image as u32, pixel);
-            }
-        }
+    /// Like [`Self::from_site_rect`] for many rectangles at once, tiles shared between
+    /// rectangles are only fetched once
+    ///
+    /// Every distinct tile stays in memory until all areas are built, 4 MB each
+    pub async fn from_site_rects<'a, S, I>(
+        source: &S,
+        rects: I,
+    ) -> Result<Vec<Self>, ImageDataError>
+    where
+        S: TileSource,
+        I: IntoIterator<Item = &'a CanvasRect>,
+    {
+        let rects = rects.into_iter().collect::<Vec<_>>();
+        let tiles = source
+            .fetch_tiles(rects.iter().flat_map(|v| v.get_tiles()))
+            .await?;
+
+        rects
+            .into_iter()
+            .map(|rect| Self::new(RegionView::new(&tiles, rect)?.to_image()))
+            .collect()
+    }
 
-        Self::new(new_image)
+    /// Stitches the area together out of already fetched tiles
+    pub fn from_tiles(
+        tiles: &HashMap<Tile, TileImage>,
+        top_left_corner: &TileCoords,
+        width: u16,
+        height: u16,
+    ) -> Result<Self, ImageDataError> {
+        let rect = CanvasRect::from_tile_coords(top_left_corner, width as u32, height as u32)?;
+        Self::new(RegionView::new(tiles, &rect)?.to_image())
     }
 
     pub fn get_image(&self) -> &image::DynamicImage {
@@ -1,30 +1,80 @@
-use std::io::Write;
-
 use curl::easy::Handler;
 
+pub mod canvas_rect;
 pub mod color;
+#[cfg(feature = "test-support")]
+pub mod fake_backend;
+pub mod global_pixel;
 pub mod image_comparison;
 pub mod image_data;
 pub mod map_coords;
 pub mod metadata;
 pub mod nominatim_data;
+pub mod pixel_info;
+pub mod rate_limiter;
+pub mod region_view;
+pub mod request;
+pub mod retry_policy;
 pub mod template_data;
+pub mod tile_cache;
 pub mod tile_coords;
 pub mod tile_downloader;
+pub mod tile_dumper;
+pub mod tile_memory_cache;
+pub mod tile_source;
+pub mod tile_validation;
+pub mod web_mercator;
+pub mod wplace_client;
 
 #[inline(always)]
 pub fn convert_px_to_hours(px: u32) -> f64 {
     (px as f64) / 120.0
 }
 
-struct GenericBytes(Vec<u8>);
-impl Handler for GenericBytes {
+/// Collects the response body along with its headers
+#[derive(Debug, Default)]
+struct GenericResponse {
+    body: Vec<u8>,
+    /// Raw header lines of the last response, without the status line
+    headers: Vec<String>,
+}
+
+impl GenericResponse {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            body: Vec::with_capacity(capacity),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Header names are compared case-insensitively
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case(name)
+                .then_some(value.trim())
+        })
+    }
+}
+
+impl Handler for GenericResponse {
     fn write(&mut self, data: &[u8]) -> Result<usize, curl::easy::WriteError> {
-        self.0
-            .write_all(data)
-            .map_err(|_| curl::easy::WriteError::Pause)?;
+        self.body.extend_from_slice(data);
         Ok(data.len())
     }
+
+    fn header(&mut self, data: &[u8]) -> bool {
+        let line = String::from_utf8_lossy(data);
+        let line = line.trim_end();
+        if line.starts_with("HTTP/") {
+            // A new response begins, e.g. after a redirect
+            self.headers.clear();
+        } else if !line.is_empty() {
+            self.headers.push(line.to_string());
+        }
+        true
+    }
 }
 
 // struct GenericBytesCursor<'a>(&'a mut std::io::Cursor<Vec<u8>>);
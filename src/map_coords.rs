@@ -1,5 +1,13 @@
-use crate::tile_coords::TileCoords;
+use crate::{
+    canvas_rect::CanvasRect, global_pixel::GlobalPixel, tile_coords::TileCoords,
+    web_mercator::WebMercatorCoords, wplace_client::WplaceClient,
+};
 
+/// Canvas pixels per screen pixel at zoom 0, the whole canvas fits in one 512 pixel map tile
+const CANVAS_PX_PER_SCREEN_PX_AT_ZOOM_0: f64 = GlobalPixel::CANVAS_SIZE as f64 / 512f64;
+
+/// Serialized as `{"lat": 0.0, "lng": 0.0, "zoom": 0.0}`
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct MapCoords {
     lat: f64,
     lng: f64,
@@ -7,6 +15,18 @@ pub struct MapCoords {
 }
 
 impl MapCoords {
+    /// Radius of the sphere Web Mercator projects, in meters
+    pub const EARTH_RADIUS: f64 = 6_378_137.0;
+
+    /// Furthest out the map zooms
+    pub const MIN_ZOOM: f32 = 0.0;
+    /// Furthest in the map zooms
+    pub const MAX_ZOOM: f32 = 22.0;
+
+    pub fn new(lat: f64, lng: f64, zoom: f32) -> Self {
+        Self { lat, lng, zoom }
+    }
+
     pub fn get_lat(&self) -> f64 {
         self.lat
     }
@@ -26,25 +46,87 @@ impl MapCoords {
     }
 
     pub fn get_nominatim_link(&self) -> String {
+        self.get_nominatim_link_for(WplaceClient::DEFAULT_NOMINATIM_ENDPOINT)
+    }
+
+    pub(crate) fn get_nominatim_link_for(&self, endpoint: &str) -> String {
         format!(
-            "https://nominatim.openstreetmap.org/reverse?lat={}&lon={}&zoom=12&format=jsonv2&layer=address&addressdetails=0&polygon_geojson=1",
+            "{endpoint}?lat={}&lon={}&zoom=12&format=jsonv2&layer=address&addressdetails=0&polygon_geojson=1",
             self.lat, self.lng
         )
     }
 
     pub fn from_tile_coords(tile_coords: &TileCoords, width: u32, height: u32) -> Self {
-        let rel_x = ((tile_coords.get_tile_x() as f64) * 1000f64 + (tile_coords.get_x() as f64))
-            / (2048f64 * 1000f64); // Relative X
-        let rel_y = 1f64
-            - ((tile_coords.get_tile_y() as f64) * 1000f64 + (tile_coords.get_y() as f64))
-                / (2048f64 * 1000f64); // Relative Y
+        Self::from_canvas_position(
+            (tile_coords.get_tile_x() as f64) * (GlobalPixel::TILE_SIZE as f64)
+                + (tile_coords.get_x() as f64),
+            (tile_coords.get_tile_y() as f64) * (GlobalPixel::TILE_SIZE as f64)
+                + (tile_coords.get_y() as f64),
+            width,
+            height,
+        )
+    }
+
+    /// Centered on `rect`, zoomed so that it and `margin` pixels around it fit in a
+    /// `viewport_width`x`viewport_height` map, see [`Self::get_zoom_to_fit`]
+    pub fn from_canvas_rect(
+        rect: &CanvasRect,
+        margin: u32,
+        viewport_width: u32,
+        viewport_height: u32,
+    ) -> Self {
         Self {
-            lat: 360f64
-                * (std::f64::consts::E.powf((rel_y * 2f64 - 1f64) * std::f64::consts::PI)).atan()
-                / std::f64::consts::PI
-                - 90f64,
-            lng: rel_x * 360f64 - 180f64,
-            zoom: match std::cmp::max(width, height) {
+            zoom: Self::get_zoom_to_fit(rect, margin, viewport_width, viewport_height),
+            ..rect.get_center()
+        }
+    }
+
+    /// The closest zoom at which `rect` and `margin` pixels around it fit in a
+    /// `viewport_width`x`viewport_height` map, in screen pixels
+    ///
+    /// Zoom `z` shows `2^z / 4000` screen pixels per canvas pixel, like on wplace.live. The
+    /// zoom is rounded down to hundredths and kept between [`Self::MIN_ZOOM`] and
+    /// [`Self::MAX_ZOOM`]
+    pub fn get_zoom_to_fit(
+        rect: &CanvasRect,
+        margin: u32,
+        viewport_width: u32,
+        viewport_height: u32,
+    ) -> f32 {
+        let width = rect.get_width() as f64 + 2f64 * margin as f64;
+        let height = rect.get_height() as f64 + 2f64 * margin as f64;
+        let scale = (viewport_width as f64 / width).min(viewport_height as f64 / height);
+        let zoom = (CANVAS_PX_PER_SCREEN_PX_AT_ZOOM_0 * scale).log2();
+        ((zoom * 100f64).floor() / 100f64).clamp(Self::MIN_ZOOM as f64, Self::MAX_ZOOM as f64)
+            as f32
+    }
+
+    pub fn from_global_pixel(pixel: GlobalPixel, width: u32, height: u32) -> Self {
+        Self::from_canvas_position(pixel.get_x() as f64, pixel.get_y() as f64, width, height)
+    }
+
+    /// Where this point is in pixels from the top-left corner of the canvas, through the inverse
+    /// of the Web Mercator projection `from_canvas_position` uses
+    ///
+    /// Points past the canvas, e.g. beyond ±85.05° of latitude, end up outside of it
+    pub(crate) fn get_canvas_position(&self) -> (f64, f64) {
+        WebMercatorCoords::from_map_coords(self).get_canvas_position()
+    }
+
+    /// Ground size of the side of a canvas pixel here
+    ///
+    /// Pixels are `2πR / 2,048,000` meters wide on the equator, Mercator's scale factor
+    /// `1 / cos(lat)` makes them smaller on the ground away from it
+    pub fn get_meters_per_pixel(&self) -> f64 {
+        2f64 * std::f64::consts::PI * Self::EARTH_RADIUS / (GlobalPixel::CANVAS_SIZE as f64)
+            * self.lat.to_radians().cos()
+    }
+
+    /// `x` and `y` are in pixels from the top-left corner of the canvas, the zoom fits a
+    /// `width`x`height` area
+    pub(crate) fn from_canvas_position(x: f64, y: f64, width: u32, height: u32) -> Self {
+        WebMercatorCoords::from_canvas_position(x, y).to_map_coords(
+            match std::cmp::max(width, height) {
                 0..1 => 22.0,
                 1..10 => 20.0,
                 10..60 => 17.0,
@@ -54,6 +136,26 @@ impl MapCoords {
                 500..1000 => 12.0,
                 _ => 11.0,
             },
-        }
+        )
+    }
+}
+
+/// A point with the longitude as `x` and the latitude as `y`
+impl From<&MapCoords> for geo_types::Point<f64> {
+    fn from(value: &MapCoords) -> Self {
+        geo_types::Point::new(value.lng, value.lat)
+    }
+}
+
+impl From<MapCoords> for geo_types::Point<f64> {
+    fn from(value: MapCoords) -> Self {
+        Self::from(&value)
+    }
+}
+
+/// Zoomed in as far as the map goes, like for a single pixel
+impl From<geo_types::Point<f64>> for MapCoords {
+    fn from(value: geo_types::Point<f64>) -> Self {
+        Self::new(value.y(), value.x(), Self::MAX_ZOOM)
     }
 }
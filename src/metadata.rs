@@ -2,7 +2,10 @@ use std::path::PathBuf;
 
 use serde::Deserialize;
 
-use crate::template_data::{TemplateData, TemplateDataError};
+use crate::{
+    template_data::{TemplateData, TemplateDataError},
+    wplace_client::WplaceClient,
+};
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -41,7 +44,7 @@ fn normalize_path_for_file<P: AsRef<std::path::Path>, T: AsRef<std::path::Path>>
         return Err(TemplateMetadataDecodeError::InvalidPath);
     }
 
-    return Ok(path);
+    Ok(path)
 }
 
 impl TemplateMetadataDecode {
@@ -60,12 +63,14 @@ impl TemplateMetadataDecode {
         normalize_path_for_file(path, &self.file_name)
     }
 
-    pub fn into_template<P: AsRef<std::path::Path>>(
+    pub async fn into_template<P: AsRef<std::path::Path>>(
         self,
+        client: &WplaceClient,
         path: P,
     ) -> Result<TemplateData, TemplateMetadataDecodeError> {
         let path = self.get_image_path(path)?;
-        TemplateData::from_data(self.name, &self.coords, path, Some(self.location))
+        TemplateData::from_data(client, self.name, &self.coords, path, Some(self.location))
+            .await
             .map_err(Into::into)
     }
 }
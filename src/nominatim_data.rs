@@ -1,8 +1,14 @@
 use geojson::PolygonType;
 
-use crate::{GenericBytes, map_coords::MapCoords};
-
-#[derive(Clone)]
+use crate::{
+    map_coords::MapCoords,
+    request::RequestError,
+    retry_policy::{Attempted, AttemptedRequestError},
+    wplace_client::WplaceClient,
+};
+
+/// Serialized as `{"display_name": ""}`
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct NominatimData {
     pub(crate) display_name: String,
 }
@@ -22,94 +28,109 @@ pub enum NominatimDataError {
         input_value: String,
     },
     #[error("GeoJSON Error: {0}")]
-    GeoJSONError(#[from] geojson::Error),
+    GeoJSONError(#[from] Box<geojson::Error>),
     #[error("Invalid GeoJSON Type: {0}")]
     GeoJSONInvalidType(&'static str),
+    #[error("Request Error: {0}")]
+    RequestError(#[from] AttemptedRequestError),
+}
+
+impl From<geojson::Error> for NominatimDataError {
+    fn from(value: geojson::Error) -> Self {
+        Self::GeoJSONError(Box::new(value))
+    }
 }
 
-static mut LAST_REQUEST: std::time::SystemTime = std::time::UNIX_EPOCH;
-static mut CACHE: Vec<(PolygonType, NominatimData, std::time::SystemTime)> = vec![];
+pub(crate) type NominatimCache = Vec<(PolygonType, NominatimData, std::time::SystemTime)>;
 
 impl NominatimData {
-    /// This function has side effects!
-    ///
-    /// It modifies a `LAST_REQUEST` global variable used to keep track of timeouts in the Nominatim API
-    ///
-    /// This *WILL* make the thread sleep!
-    pub fn load_data(v: &MapCoords) -> Result<Self, NominatimDataError> {
-        unsafe {
-            #[allow(static_mut_refs)]
-            CACHE.retain(|(_, _, t)| {
-                let v = std::time::SystemTime::now().duration_since(*t);
-                v.is_ok() && v.unwrap().as_secs() < 1200
-            });
+    async fn request(client: &WplaceClient, url: &str) -> Result<Vec<u8>, RequestError> {
+        let curl_client = client.new_request(url, 1024)?;
+        let (_, mut curl_client) = client.perform(url, curl_client).await?;
+        Ok(std::mem::take(&mut curl_client.get_mut().body))
+    }
 
-            #[allow(static_mut_refs)]
-            for (polygon, data, _) in &CACHE {
-                let Some(outer) = polygon.first() else {
+    /// Looks for a cached area containing `v`
+    fn get_from_cache(cache: &mut NominatimCache, v: &MapCoords) -> Option<Self> {
+        cache.retain(|(_, _, t)| {
+            let v = std::time::SystemTime::now().duration_since(*t);
+            v.is_ok() && v.unwrap().as_secs() < 1200
+        });
+
+        for (polygon, data, _) in cache.iter() {
+            let Some(outer) = polygon.first() else {
+                continue;
+            };
+            let mut outer = outer.iter().peekable();
+
+            let mut count = 0;
+
+            let lat = v.get_lat();
+            let lng = v.get_lng();
+
+            while let (Some(point), Some(next_point)) = (outer.next(), outer.peek()) {
+                let mut points = [
+                    (*point.first().unwrap(), *point.get(1).unwrap()),
+                    (*next_point.first().unwrap(), *next_point.get(1).unwrap()),
+                ];
+                points.sort_by(|a, b| a.1.total_cmp(&b.1));
+                let [below_point /* A */, above_point /* B */] = points;
+
+                if lat < below_point.1
+                    || lat > above_point.1
+                    || lng > f64::max(above_point.0, below_point.0)
+                {
                     continue;
-                };
-                let mut outer = outer.iter().peekable();
-
-                let mut count = 0;
-
-                let lat = v.get_lat();
-                let lng = v.get_lng();
-
-                while let (Some(point), Some(next_point)) = (outer.next(), outer.peek()) {
-                    let mut points = [
-                        (*point.first().unwrap(), *point.get(1).unwrap()),
-                        (*next_point.first().unwrap(), *next_point.get(1).unwrap()),
-                    ];
-                    points.sort_by(|a, b| a.1.total_cmp(&b.1));
-                    let [below_point /* A */, above_point /* B */] = points;
-
-                    if lat < below_point.1
-                        || lat > above_point.1
-                        || lng > f64::max(above_point.0, below_point.0)
-                    {
-                        continue;
-                    }
-
-                    let m_red = if (below_point.0 - above_point.0).abs() > 0.0 {
-                        (above_point.1 - below_point.1) / (above_point.0 - below_point.0)
-                    } else {
-                        f64::MAX
-                    };
-                    let m_blue = if (below_point.0 - lng).abs() > 0.0 {
-                        (lat - below_point.1) / (lng - below_point.0)
-                    } else {
-                        f64::MAX
-                    };
-                    if m_blue >= m_red {
-                        count += 1
-                    }
                 }
 
-                if count % 2 == 1 {
-                    return Ok(data.clone());
+                let m_red = if (below_point.0 - above_point.0).abs() > 0.0 {
+                    (above_point.1 - below_point.1) / (above_point.0 - below_point.0)
+                } else {
+                    f64::MAX
+                };
+                let m_blue = if (below_point.0 - lng).abs() > 0.0 {
+                    (lat - below_point.1) / (lng - below_point.0)
+                } else {
+                    f64::MAX
+                };
+                if m_blue >= m_red {
+                    count += 1
                 }
             }
-        }
 
-        while let v = std::time::SystemTime::now().duration_since(unsafe { LAST_REQUEST })
-            && (v.is_err() || v.unwrap().as_secs() < 1)
-        {
-            std::thread::sleep(std::time::Duration::new(1, 100));
+            if count % 2 == 1 {
+                return Some(data.clone());
+            }
         }
-        unsafe { LAST_REQUEST = std::time::SystemTime::now() };
 
-        let url = v.get_nominatim_link();
-        println!("{url}");
+        None
+    }
 
-        let mut curl_client = curl::easy::Easy2::new(GenericBytes(Vec::with_capacity(1024)));
-        curl_client.url(&url).expect("Couldn't select url");
-        curl_client
-            .useragent("Mozilla/5.0 (X11; Linux x86_64; rv:144.0) Gecko/20100101 Firefox/144.0")
-            .expect("Couldn't set user agent");
-        curl_client.perform().expect("Couldn't perform");
+    /// This function has side effects!
+    ///
+    /// Results are cached in the client, requests wait for its Nominatim rate limit budget
+    ///
+    /// Data served from the cache took 0 attempts
+    pub async fn load_data(
+        client: &WplaceClient,
+        v: &MapCoords,
+    ) -> Result<Attempted<Self>, NominatimDataError> {
+        if let Some(data) = Self::get_from_cache(&mut client.nominatim_cache.lock().unwrap(), v) {
+            return Ok(Attempted {
+                value: data,
+                attempts: 0,
+            });
+        }
+
+        let url = client.get_nominatim_url(v);
 
-        let data = curl_client.get_ref().0.clone();
+        let Attempted {
+            value: data,
+            attempts,
+        } = client
+            .get_retry_policy()
+            .run(|| Self::request(client, &url))
+            .await?;
 
         let data: NominatimDataJson = serde_json::from_slice(&data).map_err(|e| {
             NominatimDataError::JSONDeserializeError {
@@ -123,7 +144,10 @@ impl NominatimData {
         };
 
         if data.addresstype == "country" {
-            return Ok(out);
+            return Ok(Attempted {
+                value: out,
+                attempts,
+            });
         }
 
         let geojson_value = geojson::Value::from_json_value(data.geojson)?;
@@ -137,14 +161,16 @@ impl NominatimData {
             v => return Err(NominatimDataError::GeoJSONInvalidType(v.type_name())),
         };
 
+        let mut cache = client.nominatim_cache.lock().unwrap();
         for polygon in polygons {
-            unsafe {
-                #[allow(static_mut_refs)]
-                CACHE.push((polygon.clone(), out.clone(), std::time::SystemTime::now()));
-            }
+            cache.push((polygon, out.clone(), std::time::SystemTime::now()));
         }
+        drop(cache);
 
-        Ok(out)
+        Ok(Attempted {
+            value: out,
+            attempts,
+        })
     }
 
     pub fn get_display_name(&self) -> &str {
@@ -0,0 +1,214 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime},
+};
+
+use futures::{StreamExt, TryStreamExt};
+use itertools::Itertools;
+
+use crate::{
+    request::RequestError,
+    retry_policy::{Attempted, AttemptedRequestError},
+    tile_coords::TileCoords,
+    wplace_client::WplaceClient,
+};
+
+/// Who last painted a pixel, as reported by the backend
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PixelInfo {
+    pub(crate) coords: TileCoords,
+    /// `None` when the pixel was never painted
+    pub(crate) painter: Option<Painter>,
+    /// Only set when the backend reports it
+    pub(crate) painted_at: Option<SystemTime>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Painter {
+    pub(crate) id: u64,
+    pub(crate) name: String,
+    /// `None` when the painter isn't in an alliance
+    pub(crate) alliance: Option<Alliance>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Alliance {
+    pub(crate) id: u64,
+    pub(crate) name: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PixelInfoJson {
+    painted_by: Option<PaintedByJson>,
+    /// Milliseconds since the UNIX epoch
+    painted_at: Option<u64>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PaintedByJson {
+    id: u64,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    alliance_id: u64,
+    #[serde(default)]
+    alliance_name: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PixelInfoError {
+    #[error("JSON Deserialize Error: {error}; {input_value}")]
+    JSONDeserializeError {
+        error: serde_json::Error,
+        input_value: String,
+    },
+    #[error("Request Error: {0}")]
+    RequestError(#[from] AttemptedRequestError),
+}
+
+/// Lookups by pixel, along with when they were made
+pub(crate) type PixelInfoCache = HashMap<TileCoords, (PixelInfo, Instant)>;
+
+impl PixelInfo {
+    fn from_json(coords: TileCoords, data: PixelInfoJson) -> Self {
+        Self {
+            coords,
+            // The backend answers with id 0 for pixels nobody painted
+            painter: data.painted_by.filter(|v| v.id != 0).map(|v| Painter {
+                id: v.id,
+                name: v.name,
+                alliance: match v.alliance_id {
+                    0 => None,
+                    id => Some(Alliance {
+                        id,
+                        name: v.alliance_name,
+                    }),
+                },
+            }),
+            painted_at: data
+                .painted_at
+                .map(|v| SystemTime::UNIX_EPOCH + Duration::from_millis(v)),
+        }
+    }
+
+    pub fn get_coords(&self) -> &TileCoords {
+        &self.coords
+    }
+
+    pub fn get_painter(&self) -> Option<&Painter> {
+        self.painter.as_ref()
+    }
+
+    pub fn get_painted_at(&self) -> Option<SystemTime> {
+        self.painted_at
+    }
+}
+
+impl Painter {
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_alliance(&self) -> Option<&Alliance> {
+        self.alliance.as_ref()
+    }
+}
+
+impl Alliance {
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Painter attribution through the backend's pixel info endpoint
+impl WplaceClient {
+    async fn request_pixel_info(&self, url: &str) -> Result<Vec<u8>, RequestError> {
+        let curl_client = self.new_request(url, 1024)?;
+        let (_, mut curl_client) = self.perform(url, curl_client).await?;
+        Ok(std::mem::take(&mut curl_client.get_mut().body))
+    }
+
+    /// Results are cached in the client, requests wait for the backend's rate limit budget
+    ///
+    /// Info served from the cache took 0 attempts
+    pub async fn get_pixel_info(
+        &self,
+        coords: &TileCoords,
+    ) -> Result<Attempted<PixelInfo>, PixelInfoError> {
+        let ttl = self.get_pixel_info_ttl();
+        {
+            let mut cache = self.pixel_info_cache.lock().unwrap();
+            cache.retain(|_, (_, fetched_at)| fetched_at.elapsed() < ttl);
+            if let Some((info, _)) = cache.get(coords) {
+                return Ok(Attempted {
+                    value: info.clone(),
+                    attempts: 0,
+                });
+            }
+        }
+
+        let url = self.get_pixel_info_url(coords);
+        let Attempted {
+            value: data,
+            attempts,
+        } = self
+            .get_retry_policy()
+            .run(|| self.request_pixel_info(&url))
+            .await?;
+
+        let json: PixelInfoJson =
+            serde_json::from_slice(&data).map_err(|e| PixelInfoError::JSONDeserializeError {
+                error: e,
+                input_value: String::from_utf8_lossy(&data).to_string(),
+            })?;
+        let info = PixelInfo::from_json(*coords, json);
+
+        self.pixel_info_cache
+            .lock()
+            .unwrap()
+            .insert(*coords, (info.clone(), Instant::now()));
+
+        Ok(Attempted {
+            value: info,
+            attempts,
+        })
+    }
+
+    /// Looks every pixel up with at most `get_concurrency` requests in flight
+    ///
+    /// The results are in the same order as `coords`, duplicates are only looked up once
+    pub async fn get_pixels_info<I: IntoIterator<Item = TileCoords>>(
+        &self,
+        coords: I,
+    ) -> Result<Vec<PixelInfo>, PixelInfoError> {
+        let coords = coords.into_iter().collect::<Vec<_>>();
+
+        let infos: HashMap<TileCoords, PixelInfo> =
+            futures::stream::iter(coords.iter().copied().unique())
+                .map(|coords| async move {
+                    self.get_pixel_info(&coords)
+                        .await
+                        .map(|v| (coords, v.into_value()))
+                })
+                .buffer_unordered(self.get_concurrency())
+                .try_collect()
+                .await?;
+
+        Ok(coords.iter().map(|v| infos[v].clone()).collect())
+    }
+
+    /// Drops every cached lookup
+    pub fn clear_pixel_info_cache(&self) {
+        self.pixel_info_cache.lock().unwrap().clear();
+    }
+}
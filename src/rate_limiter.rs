@@ -0,0 +1,154 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+pub const TILE_BACKEND_HOST: &str = "backend.wplace.live";
+pub const NOMINATIM_HOST: &str = "nominatim.openstreetmap.org";
+
+/// A token bucket budget: up to `burst` requests at once, refilled at `per_second`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    burst: u32,
+    per_second: f64,
+}
+
+impl RateLimit {
+    /// A `burst` of 0 is treated as 1, a `per_second` that isn't positive as 1
+    pub fn new(burst: u32, per_second: f64) -> Self {
+        Self {
+            burst: burst.max(1),
+            per_second: match per_second > 0.0 {
+                true => per_second,
+                false => 1.0,
+            },
+        }
+    }
+
+    pub fn get_burst(&self) -> u32 {
+        self.burst
+    }
+
+    pub fn get_per_second(&self) -> f64 {
+        self.per_second
+    }
+}
+
+struct TokenBucket {
+    limit: RateLimit,
+    /// Goes negative when requests are queued up waiting for their turn
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Reserves a token, returning how long to wait before it may be used
+    fn reserve(&mut self) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.per_second).min(self.limit.burst as f64);
+        self.last_refill = now;
+
+        self.tokens -= 1.0;
+        match self.tokens >= 0.0 {
+            true => Duration::ZERO,
+            false => Duration::from_secs_f64(-self.tokens / self.limit.per_second),
+        }
+    }
+}
+
+/// Per-host token buckets, safe to share between threads and tasks
+///
+/// Hosts without a [`RateLimit`] aren't throttled
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl Default for RateLimiter {
+    /// Nominatim's usage policy allows 1 request per second
+    fn default() -> Self {
+        Self::new()
+            .with_limit(TILE_BACKEND_HOST, Self::DEFAULT_TILE_BACKEND_LIMIT)
+            .with_limit(NOMINATIM_HOST, Self::DEFAULT_NOMINATIM_LIMIT)
+    }
+}
+
+impl RateLimiter {
+    pub const DEFAULT_TILE_BACKEND_LIMIT: RateLimit = RateLimit {
+        burst: 8,
+        per_second: 4.0,
+    };
+    pub const DEFAULT_NOMINATIM_LIMIT: RateLimit = RateLimit {
+        burst: 1,
+        per_second: 1.0,
+    };
+
+    /// A limiter without any limit
+    pub fn new() -> Self {
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_limit(self, host: impl ToString, limit: RateLimit) -> Self {
+        self.set_limit(host, limit);
+        self
+    }
+
+    /// Replaces the budget of `host`, which starts out full
+    pub fn set_limit(&self, host: impl ToString, limit: RateLimit) {
+        self.buckets
+            .lock()
+            .unwrap()
+            .insert(host.to_string(), TokenBucket::new(limit));
+    }
+
+    pub fn remove_limit(&self, host: &str) {
+        self.buckets.lock().unwrap().remove(host);
+    }
+
+    pub fn get_limit(&self, host: &str) -> Option<RateLimit> {
+        self.buckets.lock().unwrap().get(host).map(|v| v.limit)
+    }
+
+    /// Reserves a request to `host`, returning how long to wait before making it
+    pub fn reserve(&self, host: &str) -> Duration {
+        match self.buckets.lock().unwrap().get_mut(host) {
+            Some(bucket) => bucket.reserve(),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Waits until a request to `host` may be made
+    pub async fn acquire(&self, host: &str) {
+        let delay = self.reserve(host);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// The host part of `url`, without credentials or port
+    ///
+    /// IPv6 literals keep their brackets, e.g. `[::1]`
+    pub fn get_host(url: &str) -> &str {
+        let without_scheme = url.split_once("://").map_or(url, |(_, v)| v);
+        let authority = without_scheme
+            .split(['/', '?', '#'])
+            .next()
+            .unwrap_or_default();
+        let host = authority.rsplit_once('@').map_or(authority, |(_, v)| v);
+        match host.starts_with('[') {
+            true => host.find(']').map_or(host, |end| &host[..=end]),
+            false => host.split(':').next().unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use image::{GenericImageView, Rgba, RgbaImage};
+
+use crate::{
+    canvas_rect::CanvasRect,
+    global_pixel::GlobalPixel,
+    tile_downloader::Tile,
+    tile_source::{TileImage, TileSource, TileSourceError},
+};
+
+const TILE_SIZE: u32 = GlobalPixel::TILE_SIZE;
+
+/// A rectangle of the canvas read straight out of the tiles it covers, without copying them
+///
+/// Pixels are addressed relative to the top-left corner of the region, like in an image
+///
+/// Regions going past the right edge of the canvas carry on from its left edge
+#[derive(Clone, Debug)]
+pub struct RegionView {
+    top_left_corner: GlobalPixel,
+    width: u32,
+    height: u32,
+    first_tile: Tile,
+    /// How many tiles wide the region is
+    columns: usize,
+    /// Row by row, left to right
+    tiles: Vec<TileImage>,
+}
+
+impl RegionView {
+    /// Only keeps the tiles `rect` touches out of `tiles`, which must all be 1000x1000
+    pub fn new(
+        tiles: &HashMap<Tile, TileImage>,
+        rect: &CanvasRect,
+    ) -> Result<Self, TileSourceError> {
+        let top_left_corner = rect.get_top_left_corner();
+        let first_tile = top_left_corner.get_tile();
+        let last_tile = rect.get_bottom_right_corner().get_tile();
+
+        // Not `CanvasRect::get_tiles`, tiles are laid out as `locate` finds them, so the first
+        // column comes back on the right of a misaligned rectangle as wide as the canvas
+        let columns = top_left_corner
+            .get_tile_columns(rect.get_width())
+            .collect::<Vec<_>>();
+        let mut region_tiles = Vec::new();
+        for (tile_y, &tile_x) in itertools::iproduct!(first_tile.y..=last_tile.y, &columns) {
+            let tile = Tile::new(tile_x, tile_y);
+            let image = tiles.get(&tile).ok_or(TileSourceError::MissingTile(tile))?;
+            if image.dimensions() != (TILE_SIZE, TILE_SIZE) {
+                return Err(TileSourceError::InvalidTileSize {
+                    tile,
+                    width: image.width(),
+                    height: image.height(),
+                });
+            }
+            region_tiles.push(image.clone());
+        }
+
+        Ok(Self {
+            top_left_corner,
+            width: rect.get_width(),
+            height: rect.get_height(),
+            first_tile,
+            columns: columns.len(),
+            tiles: region_tiles,
+        })
+    }
+
+    /// Fetches every tile `rect` touches from `source`
+    pub async fn from_site_rect<S: TileSource>(
+        source: &S,
+        rect: &CanvasRect,
+    ) -> Result<Self, TileSourceError> {
+        let tiles = source.fetch_tiles(rect.get_tiles()).await?;
+        Self::new(&tiles, rect)
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get_top_left_corner(&self) -> GlobalPixel {
+        self.top_left_corner
+    }
+
+    /// The tile a global pixel falls in, along with its coordinates within it
+    ///
+    /// `global_x` goes past the right edge of the canvas instead of wrapping around it
+    fn locate(&self, global_x: u32, global_y: u32) -> (&RgbaImage, u32, u32) {
+        let column = (global_x / TILE_SIZE - self.first_tile.x as u32) as usize;
+        let row = (global_y / TILE_SIZE - self.first_tile.y as u32) as usize;
+        (
+            &self.tiles[row * self.columns + column],
+            global_x % TILE_SIZE,
+            global_y % TILE_SIZE,
+        )
+    }
+
+    /// `None` outside of the region
+    pub fn get_global_pixel(&self, pixel: GlobalPixel) -> Option<Rgba<u8>> {
+        let x = (pixel.x + GlobalPixel::CANVAS_SIZE - self.top_left_corner.x)
+            % GlobalPixel::CANVAS_SIZE;
+        let y = pixel.y.checked_sub(self.top_left_corner.y)?;
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let (tile, x_in_tile, y_in_tile) = self.locate(self.top_left_corner.x + x, pixel.y);
+        Some(*tile.get_pixel(x_in_tile, y_in_tile))
+    }
+
+    /// Row `y` of the region as raw RGBA bytes, one slice per tile it crosses, left to right
+    ///
+    /// # Panics
+    /// If `y` is outside of the region
+    pub fn get_row_slices(&self, y: u32) -> impl Iterator<Item = &[u8]> {
+        assert!(y < self.height, "Row {y} is outside of the region");
+
+        let global_y = self.top_left_corner.y + y;
+        let right = self.top_left_corner.x + self.width;
+        let mut global_x = self.top_left_corner.x;
+
+        std::iter::from_fn(move || {
+            if global_x >= right {
+                return None;
+            }
+
+            let (tile, x_in_tile, y_in_tile) = self.locate(global_x, global_y);
+            let length = (TILE_SIZE - x_in_tile).min(right - global_x);
+            global_x += length;
+
+            let start = ((y_in_tile * TILE_SIZE + x_in_tile) * 4) as usize;
+            Some(&tile.as_raw()[start..start + length as usize * 4])
+        })
+    }
+
+    /// Pixels of row `y`, left to right
+    ///
+    /// # Panics
+    /// If `y` is outside of the region
+    pub fn get_row_pixels(&self, y: u32) -> impl Iterator<Item = Rgba<u8>> {
+        self.get_row_slices(y)
+            .flat_map(|v| v.chunks_exact(4))
+            .map(|v| Rgba([v[0], v[1], v[2], v[3]]))
+    }
+
+    /// Every pixel with its coordinates, row by row, without looking tiles up per pixel
+    pub fn iter_pixels(&self) -> impl Iterator<Item = (u32, u32, Rgba<u8>)> {
+        (0..self.height).flat_map(move |y| {
+            self.get_row_pixels(y)
+                .enumerate()
+                .map(move |(x, pixel)| (x as u32, y, pixel))
+        })
+    }
+
+    /// Copies the region into its own image
+    pub fn to_image(&self) -> RgbaImage {
+        let mut image = RgbaImage::new(self.width, self.height);
+        let buffer: &mut [u8] = &mut image;
+        let mut start = 0;
+        for y in 0..self.height {
+            for slice in self.get_row_slices(y) {
+                buffer[start..start + slice.len()].copy_from_slice(slice);
+                start += slice.len();
+            }
+        }
+        image
+    }
+}
+
+impl GenericImageView for RegionView {
+    type Pixel = Rgba<u8>;
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// # Panics
+    /// If the pixel is outside of the region
+    fn get_pixel(&self, x: u32, y: u32) -> Self::Pixel {
+        assert!(
+            self.in_bounds(x, y),
+            "Pixel {x}, {y} is outside of the region"
+        );
+        let (tile, x_in_tile, y_in_tile) =
+            self.locate(self.top_left_corner.x + x, self.top_left_corner.y + y);
+        *tile.get_pixel(x_in_tile, y_in_tile)
+    }
+}
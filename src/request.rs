@@ -0,0 +1,90 @@
+use curl::easy::Easy2;
+
+use crate::GenericResponse;
+
+/// How much of an unexpected response body is kept in [`RequestError::HttpStatus`]
+const BODY_PREVIEW_LENGTH: usize = 256;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RequestError {
+    #[error("Curl Error for {url}: {source}")]
+    CurlError { url: String, source: curl::Error },
+    #[error("Curl Multi Error for {url}: {source}")]
+    CurlMultiError {
+        url: String,
+        source: curl::MultiError,
+    },
+    #[error("Timed out requesting {url}")]
+    Timeout { url: String },
+    #[error("HTTP {status} for {url}: {body_preview}")]
+    HttpStatus {
+        url: String,
+        status: u32,
+        body_preview: String,
+        /// How long the server asked us to wait through `Retry-After`
+        retry_after: Option<std::time::Duration>,
+    },
+    #[error("The curl actor stopped before answering")]
+    ActorStopped,
+}
+
+impl RequestError {
+    pub(crate) fn from_curl(url: &str, error: curl::Error) -> Self {
+        match error.is_operation_timedout() {
+            true => Self::Timeout {
+                url: url.to_string(),
+            },
+            false => Self::CurlError {
+                url: url.to_string(),
+                source: error,
+            },
+        }
+    }
+
+    /// The HTTP status the server answered with, if it answered at all
+    pub fn get_status(&self) -> Option<u32> {
+        match self {
+            Self::HttpStatus { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<std::time::Duration> {
+    if let Ok(seconds) = value.parse() {
+        return Some(std::time::Duration::from_secs(seconds));
+    }
+
+    httpdate::parse_http_date(value).ok().map(|date| {
+        date.duration_since(std::time::SystemTime::now())
+            .unwrap_or_default()
+    })
+}
+
+/// Makes sure the server answered with a 2xx or a `304 Not Modified`, returning the status
+pub(crate) fn check_response(
+    url: &str,
+    curl_client: &Easy2<GenericResponse>,
+) -> Result<u32, RequestError> {
+    let status = curl_client
+        .response_code()
+        .map_err(|e| RequestError::from_curl(url, e))?;
+
+    match status {
+        200..300 | 304 => Ok(status),
+        _ => {
+            let response = curl_client.get_ref();
+            let body = &response.body;
+            Err(RequestError::HttpStatus {
+                url: url.to_string(),
+                status,
+                body_preview: String::from_utf8_lossy(&body[..body.len().min(BODY_PREVIEW_LENGTH)])
+                    .to_string(),
+                retry_after: response
+                    .get_header("Retry-After")
+                    .and_then(parse_retry_after),
+            })
+        }
+    }
+}
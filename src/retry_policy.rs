@@ -0,0 +1,198 @@
+use std::time::Duration;
+
+use crate::request::RequestError;
+
+/// How failed requests are retried
+///
+/// Rate limits (`429`), server errors and transient network failures are retried with an
+/// exponential backoff plus jitter, unless the server asked for a specific delay through
+/// `Retry-After`, which wins up to `max_retry_after`. Past it the request fails right away
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Including the first one
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    /// Longest `Retry-After` worth waiting for
+    max_retry_after: Duration,
+}
+
+/// A value along with how many requests it took to get it
+///
+/// Values served from a cache took 0 attempts
+#[derive(Clone, Copy, Debug)]
+pub struct Attempted<T> {
+    pub(crate) value: T,
+    pub(crate) attempts: u32,
+}
+
+impl<T> Attempted<T> {
+    pub fn get_value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    pub fn get_attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Attempted<U> {
+        Attempted {
+            value: f(self.value),
+            attempts: self.attempts,
+        }
+    }
+}
+
+/// A request the [`RetryPolicy`] gave up on, along with how many times it was made
+#[derive(thiserror::Error, Debug)]
+#[error("{source} after {attempts} attempt(s)")]
+pub struct AttemptedRequestError {
+    pub(crate) source: RequestError,
+    pub(crate) attempts: u32,
+}
+
+impl AttemptedRequestError {
+    /// Why the last attempt failed
+    pub fn get_source(&self) -> &RequestError {
+        &self.source
+    }
+
+    pub fn into_source(self) -> RequestError {
+        self.source
+    }
+
+    pub fn get_attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_ATTEMPTS)
+    }
+}
+
+impl RetryPolicy {
+    pub const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+    pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+    pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+    pub const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+    /// A `max_attempts` of 0 is treated as 1
+    pub const fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: if max_attempts == 0 { 1 } else { max_attempts },
+            initial_backoff: Self::DEFAULT_INITIAL_BACKOFF,
+            max_backoff: Self::DEFAULT_MAX_BACKOFF,
+            max_retry_after: Self::DEFAULT_MAX_RETRY_AFTER,
+        }
+    }
+
+    /// Gives up on the first failure
+    pub const fn no_retries() -> Self {
+        Self::new(1)
+    }
+
+    pub const fn with_initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    pub const fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub const fn with_max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    pub fn get_max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    pub fn get_initial_backoff(&self) -> Duration {
+        self.initial_backoff
+    }
+
+    pub fn get_max_backoff(&self) -> Duration {
+        self.max_backoff
+    }
+
+    pub fn get_max_retry_after(&self) -> Duration {
+        self.max_retry_after
+    }
+
+    fn is_retryable(error: &RequestError) -> bool {
+        match error {
+            RequestError::Timeout { .. } => true,
+            RequestError::CurlError { source, .. } => {
+                source.is_couldnt_connect()
+                    || source.is_couldnt_resolve_host()
+                    || source.is_send_error()
+                    || source.is_recv_error()
+                    || source.is_got_nothing()
+                    || source.is_partial_file()
+            }
+            RequestError::HttpStatus { status, .. } => {
+                matches!(status, 408 | 429 | 500 | 502 | 503 | 504)
+            }
+            RequestError::CurlMultiError { .. } | RequestError::ActorStopped => false,
+        }
+    }
+
+    /// How long to wait after the `attempts`th failed attempt, `None` means giving up
+    pub(crate) fn get_delay(&self, attempts: u32, error: &RequestError) -> Option<Duration> {
+        if attempts >= self.max_attempts || !Self::is_retryable(error) {
+            return None;
+        }
+
+        if let RequestError::HttpStatus {
+            retry_after: Some(retry_after),
+            ..
+        } = error
+        {
+            // A server asking for hours would park the caller for that long
+            return (*retry_after <= self.max_retry_after).then_some(*retry_after);
+        }
+
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempts - 1))
+            .min(self.max_backoff);
+        // Equal jitter, half of the backoff is always waited
+        let half = backoff / 2;
+        Some(half + half.mul_f64(fastrand::f64()))
+    }
+
+    pub(crate) async fn run<T, F, Fut>(
+        &self,
+        mut request: F,
+    ) -> Result<Attempted<T>, AttemptedRequestError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, RequestError>>,
+    {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match request().await {
+                Ok(value) => return Ok(Attempted { value, attempts }),
+                Err(e) => match self.get_delay(attempts, &e) {
+                    Some(delay) => tokio::time::sleep(delay).await,
+                    None => {
+                        return Err(AttemptedRequestError {
+                            source: e,
+                            attempts,
+                        });
+                    }
+                },
+            }
+        }
+    }
+}
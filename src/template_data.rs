@@ -1,8 +1,13 @@
 use crate::{
+    canvas_rect::{CanvasRect, CanvasRectError},
+    global_pixel::{GlobalPixel, GlobalPixelError},
     image_data::{ImageData, ImageDataError},
     map_coords::MapCoords,
     nominatim_data::{NominatimData, NominatimDataError},
+    region_view::RegionView,
     tile_coords::{TileCoords, TileCoordsError},
+    tile_source::{TileSource, TileSourceError},
+    wplace_client::WplaceClient,
 };
 
 pub enum LocationData {
@@ -13,7 +18,7 @@ pub enum LocationData {
 impl LocationData {
     pub fn get_name(&self) -> &str {
         match self {
-            Self::Name(v) => &v,
+            Self::Name(v) => v,
             Self::Nominatim(v) => &v.display_name,
         }
     }
@@ -22,6 +27,7 @@ impl LocationData {
 pub struct TemplateData {
     name: String,
     top_left_corner: TileCoords,
+    canvas_rect: CanvasRect,
     center_coordinates: MapCoords,
     location_data: LocationData,
     image: ImageData,
@@ -38,12 +44,20 @@ pub enum TemplateDataError {
     TileCoordsError(#[from] TileCoordsError),
     #[error("NominatimData Error: {0}")]
     NominatimDataError(#[from] NominatimDataError),
+    #[error("TileSource Error: {0}")]
+    TileSourceError(#[from] TileSourceError),
+    #[error("GlobalPixel Error: {0}")]
+    GlobalPixelError(#[from] GlobalPixelError),
+    #[error("CanvasRect Error: {0}")]
+    CanvasRectError(#[from] CanvasRectError),
     #[error("No file name")]
     NoFileName,
 }
 
 impl TemplateData {
-    pub fn from_data<P: AsRef<std::path::Path>>(
+    /// `client` is used to look the location up when `location_name` is `None`
+    pub async fn from_data<P: AsRef<std::path::Path>>(
+        client: &WplaceClient,
         name: impl ToString,
         top_left_corner_coords_str: &str,
         file_path: P,
@@ -57,34 +71,41 @@ impl TemplateData {
             .to_str()
             .ok_or(TemplateDataError::NoFileName)?;
         let image = ImageData::new(std::fs::read(file_path)?.as_slice())?;
-        Self::new(name, top_left_corner, file_name, image, location_name)
+        Self::new(
+            client,
+            name,
+            top_left_corner,
+            file_name,
+            image,
+            location_name,
+        )
+        .await
     }
 
-    pub fn new(
+    /// `client` is used to look the location up when `location_name` is `None`
+    pub async fn new(
+        client: &WplaceClient,
         name: impl ToString,
         top_left_corner: TileCoords,
         file_name: impl ToString,
         image: ImageData,
         location_name: Option<String>,
     ) -> Result<Self, TemplateDataError> {
-        let center_coordinates = MapCoords::from_tile_coords(
-            &TileCoords {
-                tile_x: top_left_corner.tile_x,
-                tile_y: top_left_corner.tile_y,
-                x: top_left_corner.x + (image.width as u16),
-                y: top_left_corner.y + (image.height as u16),
-            },
+        let canvas_rect = CanvasRect::new(
+            GlobalPixel::try_from(top_left_corner)?,
             image.width,
             image.height,
-        );
+        )?;
+        let center_coordinates = canvas_rect.get_center();
         Ok(Self {
             name: name.to_string(),
             file_name: file_name.to_string(),
             top_left_corner,
+            canvas_rect,
             location_data: match location_name {
                 Some(name) => LocationData::Name(name),
-                None => match NominatimData::load_data(&center_coordinates) {
-                    Ok(v) => LocationData::Nominatim(v),
+                None => match NominatimData::load_data(client, &center_coordinates).await {
+                    Ok(v) => LocationData::Nominatim(v.into_value()),
                     Err(NominatimDataError::JSONDeserializeError {
                         error: _,
                         input_value: s,
@@ -101,13 +122,59 @@ impl TemplateData {
         })
     }
 
-    pub fn download_template_area_on_map(&self) -> Result<ImageData, TemplateDataError> {
-        ImageData::from_site_coords(
-            &self.top_left_corner,
-            self.image.width as u16,
-            self.image.height as u16,
-        )
-        .map_err(From::from)
+    /// The area of the canvas the template covers
+    pub fn get_canvas_rect(&self) -> CanvasRect {
+        self.canvas_rect
+    }
+
+    /// Where to point the map to show the whole template in a `viewport_width`x`viewport_height`
+    /// map, with `margin` pixels around it, see [`MapCoords::from_canvas_rect`]
+    pub fn get_map_coords_to_fit(
+        &self,
+        margin: u32,
+        viewport_width: u32,
+        viewport_height: u32,
+    ) -> MapCoords {
+        MapCoords::from_canvas_rect(&self.canvas_rect, margin, viewport_width, viewport_height)
+    }
+
+    pub async fn download_template_area_on_map<S: TileSource>(
+        &self,
+        source: &S,
+    ) -> Result<ImageData, TemplateDataError> {
+        ImageData::from_site_rect(source, &self.canvas_rect)
+            .await
+            .map_err(From::from)
+    }
+
+    /// Like [`Self::download_template_area_on_map`], without copying the area out of the tiles
+    pub async fn view_template_area_on_map<S: TileSource>(
+        &self,
+        source: &S,
+    ) -> Result<RegionView, TemplateDataError> {
+        RegionView::from_site_rect(source, &self.canvas_rect)
+            .await
+            .map_err(From::from)
+    }
+
+    /// Downloads the area under every template, fetching tiles shared between them only once
+    ///
+    /// The images are in the same order as `templates`
+    pub async fn download_template_areas_on_map<'a, S, I>(
+        templates: I,
+        source: &S,
+    ) -> Result<Vec<ImageData>, TemplateDataError>
+    where
+        S: TileSource,
+        I: IntoIterator<Item = &'a TemplateData>,
+    {
+        let rects = templates
+            .into_iter()
+            .map(|template| template.canvas_rect)
+            .collect::<Vec<_>>();
+        ImageData::from_site_rects(source, &rects)
+            .await
+            .map_err(From::from)
     }
 
     pub fn get_name(&self) -> &str {
@@ -130,7 +197,29 @@ impl TemplateData {
         &self.file_name
     }
 
+    /// The center of the template, where its location was looked up
     pub fn get_center_coordiantes(&self) -> &MapCoords {
         &self.center_coordinates
     }
+
+    /// See [`CanvasRect::get_corners`]
+    pub fn get_corner_coordinates(&self) -> [MapCoords; 4] {
+        self.canvas_rect.get_corners()
+    }
+
+    /// See [`CanvasRect::get_footprint`]
+    pub fn get_footprint(&self) -> Vec<MapCoords> {
+        self.canvas_rect.get_footprint()
+    }
+
+    /// Ground area covered by the template, transparent pixels included, see
+    /// [`CanvasRect::get_area_km2`]
+    pub fn get_area_km2(&self) -> f64 {
+        self.canvas_rect.get_area_km2()
+    }
+
+    /// Ground size of the side of a pixel at the center of the template
+    pub fn get_meters_per_pixel(&self) -> f64 {
+        self.canvas_rect.get_meters_per_pixel()
+    }
 }
@@ -0,0 +1,341 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::tile_downloader::Tile;
+
+/// Persists downloaded tiles as `{x}/{y}.png`, next to a `{x}/{y}.json` with their validators
+///
+/// Entries younger than `max_age` are served as they are, older ones get revalidated
+/// through a conditional request
+#[derive(Clone, Debug)]
+pub struct DiskTileCache {
+    directory: PathBuf,
+    max_age: Duration,
+    /// Upper bound for the total size of the stored PNGs, in bytes
+    max_size: u64,
+    /// Read from the directory on the first store, then kept up to date, shared between clones
+    ///
+    /// Tiles stored by other processes are only seen by the next load
+    index: Arc<Mutex<Option<CacheIndex>>>,
+}
+
+/// What's stored, so enforcing `max_size` doesn't have to go through the whole directory
+#[derive(Debug, Default)]
+struct CacheIndex {
+    /// When each tile was fetched, and how big its PNG is
+    entries: HashMap<Tile, (u64, u64)>,
+    total_size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct CachedTileMetadata {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Seconds since the UNIX epoch
+    fetched_at: u64,
+}
+
+pub(crate) struct CachedTile {
+    pub(crate) data: Vec<u8>,
+    metadata: CachedTileMetadata,
+}
+
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|v| v.as_secs())
+        .unwrap_or(0)
+}
+
+/// Writes to a temporary file first, so readers never see half-written files
+///
+/// Every write gets its own temporary file, concurrent writers of the same file don't step on
+/// each other and the last rename wins
+pub(crate) async fn write_atomically(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let written = match tokio::fs::write(&temp_path, data).await {
+        Ok(()) => tokio::fs::rename(&temp_path, path).await,
+        Err(e) => Err(e),
+    };
+    if written.is_err() {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+    }
+    written
+}
+
+/// Another task may have evicted the same file already
+async fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        v => v,
+    }
+}
+
+impl CacheIndex {
+    fn insert(&mut self, tile: Tile, fetched_at: u64, size: u64) {
+        if let Some((_, old_size)) = self.entries.insert(tile, (fetched_at, size)) {
+            self.total_size -= old_size;
+        }
+        self.total_size += size;
+    }
+
+    /// Forgets the least recently fetched tiles until the rest fits in `max_size`
+    fn evict(&mut self, max_size: u64) -> Vec<Tile> {
+        if self.total_size <= max_size {
+            return Vec::new();
+        }
+
+        let mut entries = self
+            .entries
+            .iter()
+            .map(|(tile, (fetched_at, _))| (*fetched_at, *tile))
+            .collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|(fetched_at, _)| *fetched_at);
+
+        let mut evicted = Vec::new();
+        for (_, tile) in entries {
+            if self.total_size <= max_size {
+                break;
+            }
+            if let Some((_, size)) = self.entries.remove(&tile) {
+                self.total_size -= size;
+            }
+            evicted.push(tile);
+        }
+        evicted
+    }
+}
+
+impl CachedTile {
+    pub(crate) fn is_fresh(&self, max_age: Duration) -> bool {
+        now_secs().saturating_sub(self.metadata.fetched_at) < max_age.as_secs()
+    }
+
+    /// The `If-None-Match`/`If-Modified-Since` headers to revalidate this entry with
+    pub(crate) fn conditional_headers(&self) -> Vec<String> {
+        let mut out = Vec::with_capacity(2);
+        if let Some(etag) = &self.metadata.etag {
+            out.push(format!("If-None-Match: {etag}"));
+        }
+        if let Some(last_modified) = &self.metadata.last_modified {
+            out.push(format!("If-Modified-Since: {last_modified}"));
+        }
+        out
+    }
+}
+
+impl DiskTileCache {
+    pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(360);
+    pub const DEFAULT_MAX_SIZE: u64 = 512 * 1024 * 1024;
+
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        Self {
+            directory: directory.into(),
+            max_age: Self::DEFAULT_MAX_AGE,
+            max_size: Self::DEFAULT_MAX_SIZE,
+            index: Arc::default(),
+        }
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn get_max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    pub fn get_max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    fn get_tile_path(&self, tile: Tile, extension: &str) -> PathBuf {
+        self.directory
+            .join(tile.x.to_string())
+            .join(format!("{}.{extension}", tile.y))
+    }
+
+    /// Entries with missing or unreadable metadata are treated as absent
+    pub(crate) async fn get(&self, tile: Tile) -> std::io::Result<Option<CachedTile>> {
+        let metadata = match tokio::fs::read(self.get_tile_path(tile, "json")).await {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let Ok(metadata) = serde_json::from_slice(&metadata) else {
+            return Ok(None);
+        };
+
+        match tokio::fs::read(self.get_tile_path(tile, "png")).await {
+            Ok(data) => Ok(Some(CachedTile { data, metadata })),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub(crate) async fn store(
+        &self,
+        tile: Tile,
+        data: &[u8],
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> std::io::Result<()> {
+        let png_path = self.get_tile_path(tile, "png");
+        if let Some(parent) = png_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        write_atomically(&png_path, data).await?;
+
+        let fetched_at = now_secs();
+        self.write_metadata(
+            tile,
+            &CachedTileMetadata {
+                etag: etag.map(ToString::to_string),
+                last_modified: last_modified.map(ToString::to_string),
+                fetched_at,
+            },
+        )
+        .await?;
+
+        self.enforce_max_size(tile, fetched_at, data.len() as u64)
+            .await
+    }
+
+    /// Marks an entry as fresh again, after the server answered `304 Not Modified`
+    pub(crate) async fn refresh(&self, tile: Tile, cached: &CachedTile) -> std::io::Result<()> {
+        let fetched_at = now_secs();
+        self.write_metadata(
+            tile,
+            &CachedTileMetadata {
+                fetched_at,
+                ..cached.metadata.clone()
+            },
+        )
+        .await?;
+
+        if let Some(index) = self.index.lock().unwrap().as_mut()
+            && let Some(entry) = index.entries.get_mut(&tile)
+        {
+            entry.0 = fetched_at;
+        }
+        Ok(())
+    }
+
+    async fn write_metadata(
+        &self,
+        tile: Tile,
+        metadata: &CachedTileMetadata,
+    ) -> std::io::Result<()> {
+        write_atomically(
+            &self.get_tile_path(tile, "json"),
+            &serde_json::to_vec(metadata)?,
+        )
+        .await
+    }
+
+    /// Goes through the whole directory, only done once
+    async fn load_index(&self) -> std::io::Result<CacheIndex> {
+        let mut index = CacheIndex::default();
+
+        let mut columns = tokio::fs::read_dir(&self.directory).await?;
+        while let Some(column) = columns.next_entry().await? {
+            let Some(x) = column.file_name().to_str().and_then(|v| v.parse().ok()) else {
+                continue;
+            };
+            if !column.file_type().await?.is_dir() {
+                continue;
+            }
+            let mut files = tokio::fs::read_dir(column.path()).await?;
+            while let Some(file) = files.next_entry().await? {
+                let json_path = file.path();
+                if json_path.extension().is_none_or(|v| v != "json") {
+                    continue;
+                }
+                let Some(y) = json_path
+                    .file_stem()
+                    .and_then(|v| v.to_str())
+                    .and_then(|v| v.parse().ok())
+                else {
+                    continue;
+                };
+                let Ok(png_metadata) = tokio::fs::metadata(json_path.with_extension("png")).await
+                else {
+                    continue;
+                };
+                let fetched_at = tokio::fs::read(&json_path)
+                    .await
+                    .ok()
+                    .and_then(|v| serde_json::from_slice::<CachedTileMetadata>(&v).ok())
+                    .map_or(0, |v| v.fetched_at);
+
+                index.insert(Tile::new(x, y), fetched_at, png_metadata.len());
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Records a stored tile, then removes the least recently fetched tiles until the cache
+    /// fits in `max_size`
+    async fn enforce_max_size(
+        &self,
+        tile: Tile,
+        fetched_at: u64,
+        size: u64,
+    ) -> std::io::Result<()> {
+        if self.index.lock().unwrap().is_none() {
+            let loaded = self.load_index().await?;
+            self.index.lock().unwrap().get_or_insert(loaded);
+        }
+
+        let evicted = {
+            let mut index = self.index.lock().unwrap();
+            let index = index.get_or_insert_default();
+            index.insert(tile, fetched_at, size);
+            index.evict(self.max_size)
+        };
+
+        for tile in evicted {
+            remove_if_exists(&self.get_tile_path(tile, "json")).await?;
+            remove_if_exists(&self.get_tile_path(tile, "png")).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes every stored tile
+    pub async fn clear(&self) -> std::io::Result<()> {
+        match tokio::fs::remove_dir_all(&self.directory).await {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            v => v?,
+        }
+        *self.index.lock().unwrap() = Some(CacheIndex::default());
+        Ok(())
+    }
+}
@@ -1,6 +1,12 @@
 use std::fmt::Display;
 
-#[derive(Debug)]
+use crate::{
+    global_pixel::{GlobalPixel, GlobalPixelError},
+    map_coords::MapCoords,
+};
+
+/// Serialized as `{"tile_x": 0, "tile_y": 0, "x": 0, "y": 0}`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct TileCoords {
     pub(crate) tile_x: u16,
     pub(crate) tile_y: u16,
@@ -8,70 +14,306 @@ pub struct TileCoords {
     pub(crate) y: u16,
 }
 
-#[derive(thiserror::Error, Debug)]
+#[derive(thiserror::Error, Debug, PartialEq)]
 pub enum TileCoordsError {
-    #[error("Parse Error: {0}")]
-    ParseError(#[from] std::num::ParseIntError),
+    #[error("Expected {expected} at byte {offset}")]
+    UnexpectedToken {
+        offset: usize,
+        expected: &'static str,
+    },
+    #[error("{field} given twice, again at byte {offset}")]
+    DuplicateField { offset: usize, field: &'static str },
+    #[error("{field} is missing")]
+    MissingField { field: &'static str },
+    #[error("{field} at byte {offset} is {value}, more than {max}")]
+    OutOfRange {
+        offset: usize,
+        field: &'static str,
+        value: u64,
+        max: u64,
+    },
+    #[error("{lat}, {lng} isn't on the canvas")]
+    OutOfCanvas { lat: f64, lng: f64 },
 }
 
-impl TileCoords {
-    #[inline(always)]
-    pub fn new(tile_x: u16, tile_y: u16, x: u16, y: u16) -> Self {
-        TileCoords {
-            tile_x,
-            tile_y,
-            x,
-            y,
+/// The ways coordinates get pasted around, see [`TileCoords::detect_format`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileCoordsFormat {
+    /// `(Tl X: 1094, Tl Y: 759, Px X: 12, Px Y: 999)`, what the `Display` impl and overlay
+    /// userscripts write, labels may also be `Tile`/`Chunk` and `Pixel`/`Pos`
+    Labeled,
+    /// `1094, 759, 12, 999`, optionally in parentheses or brackets
+    Tuple,
+    /// `https://wplace.live/?lat=41.9&lng=12.5&zoom=14`, what [`MapCoords::get_link`] writes
+    ShareLink,
+    /// `https://backend.wplace.live/s0/pixel/1094/759?x=12&y=999`, what the site requests when
+    /// clicking a pixel
+    PixelUrl,
+}
+
+/// The fields of `(Tl X: a, Tl Y: b, Px X: c, Px Y: d)`, with the largest value they can take
+const FIELDS: [(&str, &str, u64); 4] = [
+    ("Tl", "X", GlobalPixel::CANVAS_TILES as u64 - 1),
+    ("Tl", "Y", GlobalPixel::CANVAS_TILES as u64 - 1),
+    ("Px", "X", GlobalPixel::TILE_SIZE as u64 - 1),
+    ("Px", "Y", GlobalPixel::TILE_SIZE as u64 - 1),
+];
+const FIELD_NAMES: [&str; 4] = ["Tl X", "Tl Y", "Px X", "Px Y"];
+
+/// Reads `TileCoords` written like its `Display` impl does, one byte at a time
+struct TileCoordsParser<'a> {
+    input: &'a [u8],
+    offset: usize,
+}
+
+impl TileCoordsParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .input
+            .get(self.offset)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.offset += 1;
         }
     }
 
-    pub fn parse_tile_coords_string(v: &str) -> Result<Self, TileCoordsError> {
-        let mut buff = String::with_capacity(4);
-
-        let mut out = Self::new(0, 0, 0, 0);
-
-        let mut is_writing = false;
-        let mut last_character = ' ';
-        let mut counter = 0;
-        for character in v.chars() {
-            if last_character == ':' && character == ' ' {
-                is_writing = true;
-                counter += 1;
-                continue;
-            } else if character == ',' || character == ')' {
-                match counter {
-                    1 => out.tile_x = buff.parse()?,
-                    2 => out.tile_y = buff.parse()?,
-                    3 => out.x = buff.parse()?,
-                    4 => {
-                        out.y = buff.parse()?;
-                        break;
-                    }
-                    _ => unreachable!(),
+    fn unexpected(&self, expected: &'static str) -> TileCoordsError {
+        TileCoordsError::UnexpectedToken {
+            offset: self.offset,
+            expected,
+        }
+    }
+
+    /// Consumes `byte` if it is next
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.input.get(self.offset) == Some(&byte);
+        self.offset += found as usize;
+        found
+    }
+
+    fn expect(&mut self, byte: u8, expected: &'static str) -> Result<(), TileCoordsError> {
+        match self.eat(byte) {
+            true => Ok(()),
+            false => Err(self.unexpected(expected)),
+        }
+    }
+
+    /// Consumes `word` if it is next, ignoring case
+    fn eat_word(&mut self, word: &str) -> bool {
+        self.skip_whitespace();
+        let found = self
+            .input
+            .get(self.offset..self.offset + word.len())
+            .is_some_and(|v| v.eq_ignore_ascii_case(word.as_bytes()));
+        self.offset += found as usize * word.len();
+        found
+    }
+
+    /// Which of [`FIELDS`] comes next
+    fn field(&mut self) -> Result<usize, TileCoordsError> {
+        let expected = "`Tl X`, `Tl Y`, `Px X` or `Px Y`";
+        let start = self.offset;
+        let kind = if ["Tile", "Chunk", "Tl"].iter().any(|v| self.eat_word(v)) {
+            "Tl"
+        } else if ["Pixel", "Pos", "Px"].iter().any(|v| self.eat_word(v)) {
+            "Px"
+        } else {
+            return Err(self.unexpected(expected));
+        };
+
+        let axis = if self.eat_word("X") {
+            "X"
+        } else if self.eat_word("Y") {
+            "Y"
+        } else {
+            self.offset = start;
+            return Err(self.unexpected(expected));
+        };
+
+        Ok(FIELDS
+            .iter()
+            .position(|(k, a, _)| *k == kind && *a == axis)
+            .unwrap_or_default())
+    }
+
+    fn number(&mut self, field: usize) -> Result<u16, TileCoordsError> {
+        self.skip_whitespace();
+        let start = self.offset;
+        let mut value: u64 = 0;
+        while let Some(digit) = self.input.get(self.offset).filter(|v| v.is_ascii_digit()) {
+            value = value
+                .saturating_mul(10)
+                .saturating_add((digit - b'0') as u64);
+            self.offset += 1;
+        }
+
+        if self.offset == start {
+            return Err(self.unexpected("a number"));
+        }
+
+        let (_, _, max) = FIELDS[field];
+        match value <= max {
+            true => Ok(value as u16),
+            false => Err(TileCoordsError::OutOfRange {
+                offset: start,
+                field: FIELD_NAMES[field],
+                value,
+                max,
+            }),
+        }
+    }
+
+    /// `a, b, c, d`, separated by commas or whitespace, optionally in `()` or `[]`
+    fn parse_tuple(mut self) -> Result<TileCoords, TileCoordsError> {
+        let closing = match (self.eat(b'('), self.eat(b'[')) {
+            (true, _) => Some((b')', "`)`")),
+            (_, true) => Some((b']', "`]`")),
+            _ => None,
+        };
+
+        let mut values = [0; 4];
+        for (field, value) in values.iter_mut().enumerate() {
+            if field > 0 {
+                let start = self.offset;
+                self.eat(b',');
+                if self.offset == start {
+                    return Err(self.unexpected("`,` or whitespace"));
                 }
-                is_writing = false;
-                buff.clear();
-                continue;
+            }
+            *value = self.number(field)?;
+        }
+
+        if let Some((byte, expected)) = closing {
+            self.expect(byte, expected)?;
+        }
+        self.skip_whitespace();
+        if self.offset != self.input.len() {
+            return Err(self.unexpected("the end of the input"));
+        }
+
+        let [tile_x, tile_y, x, y] = values;
+        Ok(TileCoords::new(tile_x, tile_y, x, y))
+    }
+
+    /// The value of `key` in the query string, along with its offset
+    fn query_value(&self, key: &str) -> Option<(usize, &str)> {
+        let input = std::str::from_utf8(self.input).ok()?;
+        let query_start = input.find('?')? + 1;
+        let mut offset = query_start;
+        for pair in input[query_start..].split(['&', '#']) {
+            if let Some(value) = pair.strip_prefix(key).and_then(|v| v.strip_prefix('=')) {
+                return Some((offset + key.len() + 1, value.trim_end()));
+            }
+            offset += pair.len() + 1;
+        }
+        None
+    }
+
+    fn float_query_value(&self, keys: &[&'static str]) -> Result<f64, TileCoordsError> {
+        let (offset, value) = keys
+            .iter()
+            .find_map(|v| self.query_value(v))
+            .ok_or(TileCoordsError::MissingField { field: keys[0] })?;
+        value.parse().map_err(|_| TileCoordsError::UnexpectedToken {
+            offset,
+            expected: "a number",
+        })
+    }
+
+    /// `...?lat=a&lng=b&zoom=c`, `lon` is accepted for `lng` and `zoom` is ignored
+    fn parse_share_link(self) -> Result<TileCoords, TileCoordsError> {
+        let lat = self.float_query_value(&["lat"])?;
+        let lng = self.float_query_value(&["lng", "lon"])?;
+        TileCoords::from_map_coords(&MapCoords::new(lat, lng, 0.0))
+            .map_err(|_| TileCoordsError::OutOfCanvas { lat, lng })
+    }
+
+    /// `.../pixel/a/b?x=c&y=d`, the query may hold other parameters
+    fn parse_pixel_url(mut self) -> Result<TileCoords, TileCoordsError> {
+        let input = std::str::from_utf8(self.input).unwrap_or_default();
+        let Some(start) = input.find("/pixel/") else {
+            return Err(self.unexpected("`/pixel/`"));
+        };
+
+        self.offset = start + "/pixel/".len();
+        let tile_x = self.number(0)?;
+        self.expect(b'/', "`/`")?;
+        let tile_y = self.number(1)?;
+        self.expect(b'?', "`?`")?;
+
+        let mut pixel = [0; 2];
+        for (i, key) in ["x", "y"].into_iter().enumerate() {
+            let Some((offset, _)) = self.query_value(key) else {
+                return Err(TileCoordsError::MissingField {
+                    field: FIELD_NAMES[i + 2],
+                });
             };
+            self.offset = offset;
+            pixel[i] = self.number(i + 2)?;
+            self.skip_whitespace();
+            if !matches!(self.input.get(self.offset), None | Some(b'&' | b'#')) {
+                return Err(self.unexpected("`&`, `#` or the end of the input"));
+            }
+        }
 
-            if is_writing {
-                match counter {
-                    1..=4 => buff.push(character),
-                    _ => unreachable!(),
-                }
+        Ok(TileCoords::new(tile_x, tile_y, pixel[0], pixel[1]))
+    }
+
+    /// `(Tl X: a, Tl Y: b, Px X: c, Px Y: d)`, in any order, with or without the parentheses
+    fn parse(mut self) -> Result<TileCoords, TileCoordsError> {
+        let parenthesized = self.eat(b'(');
+        let mut values: [Option<u16>; 4] = [None; 4];
+
+        loop {
+            self.skip_whitespace();
+            let start = self.offset;
+            let field = self.field()?;
+            self.expect(b':', "`:`")?;
+            let value = self.number(field)?;
+
+            if values[field].replace(value).is_some() {
+                return Err(TileCoordsError::DuplicateField {
+                    offset: start,
+                    field: FIELD_NAMES[field],
+                });
             }
 
-            last_character = character;
+            if !self.eat(b',') {
+                break;
+            }
+            // A trailing comma
+            self.skip_whitespace();
+            if matches!(self.input.get(self.offset), None | Some(b')')) {
+                break;
+            }
         }
 
-        Ok(out)
+        if parenthesized {
+            self.expect(b')', "`,` or `)`")?;
+        }
+        self.skip_whitespace();
+        if self.offset != self.input.len() {
+            return Err(self.unexpected(match parenthesized {
+                true => "the end of the input",
+                false => "`,` or the end of the input",
+            }));
+        }
+
+        match values {
+            [Some(tile_x), Some(tile_y), Some(x), Some(y)] => {
+                Ok(TileCoords::new(tile_x, tile_y, x, y))
+            }
+            _ => Err(TileCoordsError::MissingField {
+                field: FIELD_NAMES[values.iter().position(Option::is_none).unwrap_or_default()],
+            }),
+        }
     }
+}
 
-    pub fn sum_x_y(&self, x: u16, y: u16) -> TileCoords {
-        let tile_x = self.tile_x + ((self.x + x) / 1000);
-        let tile_y = self.tile_y + ((self.y + y) / 1000);
-        let x = (self.x + x) % 1000;
-        let y = (self.y + y) % 1000;
+impl TileCoords {
+    #[inline(always)]
+    pub fn new(tile_x: u16, tile_y: u16, x: u16, y: u16) -> Self {
         TileCoords {
             tile_x,
             tile_y,
@@ -80,6 +322,64 @@ impl TileCoords {
         }
     }
 
+    /// Parses what the `Display` impl writes, i.e. `(Tl X: a, Tl Y: b, Px X: c, Px Y: d)`
+    ///
+    /// Fields may come in any order, with any whitespace around them, labels ignore case and the
+    /// parentheses are optional
+    ///
+    /// Use [`str::parse`] to accept every [`TileCoordsFormat`]
+    pub fn parse_tile_coords_string(v: &str) -> Result<Self, TileCoordsError> {
+        Self::parse_with_format(v, TileCoordsFormat::Labeled)
+    }
+
+    /// The pixel under a point, see [`GlobalPixel::from_map_coords`]
+    ///
+    /// Round trips with [`MapCoords::from_tile_coords`]
+    pub fn from_map_coords(map_coords: &MapCoords) -> Result<Self, GlobalPixelError> {
+        GlobalPixel::from_map_coords(map_coords).map(From::from)
+    }
+
+    /// Guesses the format from the first characters and the URL parts `v` contains
+    pub fn detect_format(v: &str) -> TileCoordsFormat {
+        let trimmed = v.trim_start().trim_start_matches(['(', '[']).trim_start();
+        if v.contains("/pixel/") {
+            TileCoordsFormat::PixelUrl
+        } else if v.contains("lat=") {
+            TileCoordsFormat::ShareLink
+        } else if trimmed.starts_with(|c: char| c.is_ascii_digit()) {
+            TileCoordsFormat::Tuple
+        } else {
+            TileCoordsFormat::Labeled
+        }
+    }
+
+    pub fn parse_with_format(v: &str, format: TileCoordsFormat) -> Result<Self, TileCoordsError> {
+        let parser = TileCoordsParser {
+            input: v.as_bytes(),
+            offset: 0,
+        };
+        match format {
+            TileCoordsFormat::Labeled => parser.parse(),
+            TileCoordsFormat::Tuple => parser.parse_tuple(),
+            TileCoordsFormat::ShareLink => parser.parse_share_link(),
+            TileCoordsFormat::PixelUrl => parser.parse_pixel_url(),
+        }
+    }
+
+    /// Moves `x` pixels right and `y` pixels down, across tiles
+    ///
+    /// Going past the right edge of the canvas carries on from its left edge, like the map does
+    pub fn sum_x_y(&self, x: u16, y: u16) -> Result<TileCoords, GlobalPixelError> {
+        let pixel = GlobalPixel::try_from(self)?;
+        pixel
+            .horizontal_wrapping_add(x as u32, y as u32)
+            .map(From::from)
+            .ok_or(GlobalPixelError::OutOfCanvas {
+                x: pixel.x as u64 + x as u64,
+                y: pixel.y as u64 + y as u64,
+            })
+    }
+
     pub fn get_x(&self) -> u16 {
         self.x
     }
@@ -94,6 +394,15 @@ impl TileCoords {
     }
 }
 
+impl std::str::FromStr for TileCoords {
+    type Err = TileCoordsError;
+
+    /// Accepts every [`TileCoordsFormat`]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_with_format(s, Self::detect_format(s))
+    }
+}
+
 impl Display for TileCoords {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -1,72 +1,233 @@
-use image::DynamicImage;
+use std::sync::Arc;
 
-use crate::GenericBytes;
+use crate::{
+    GenericResponse,
+    request::RequestError,
+    retry_policy::{Attempted, AttemptedRequestError},
+    tile_cache::CachedTile,
+    tile_source::{TileImage, TileSource, TileSourceError},
+    tile_validation::{self, TileValidationError},
+    wplace_client::WplaceClient,
+};
 
-#[derive(Hash, PartialEq, Eq, Clone, Copy)]
-struct Tile {
-    x: u16,
-    y: u16,
+#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Tile {
+    pub(crate) x: u16,
+    pub(crate) y: u16,
 }
 
 impl Tile {
-    fn url(self) -> String {
-        format!(
-            "https://backend.wplace.live/files/s0/tiles/{}/{}.png",
-            self.x, self.y
-        )
-    }
-    const fn new(x: u16, y: u16) -> Self {
+    pub const fn new(x: u16, y: u16) -> Self {
         Self { x, y }
     }
+    pub fn get_x(&self) -> u16 {
+        self.x
+    }
+    pub fn get_y(&self) -> u16 {
+        self.y
+    }
 }
 
-struct DownloadData {
-    image: DynamicImage,
-    last_download: std::time::SystemTime,
+#[derive(thiserror::Error, Debug)]
+pub enum TileDownloadError {
+    #[error("Request Error: {0}")]
+    RequestError(#[from] AttemptedRequestError),
+    #[error("Image Error: {0}")]
+    ImageError(#[from] image::error::ImageError),
+    #[error("Disk cache I/O Error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Invalid tile {}, {}: {source}", .tile.x, .tile.y)]
+    InvalidTile {
+        tile: Tile,
+        source: TileValidationError,
+    },
 }
 
-type TileDownloadCache = std::collections::HashMap<Tile, DownloadData>;
-static mut TILE_DOWNLOAD_CACHE: std::sync::LazyLock<std::sync::RwLock<TileDownloadCache>> =
-    std::sync::LazyLock::new(|| std::sync::RwLock::new(std::collections::HashMap::new()));
-
-pub enum TileDownloadError {}
-
-/// This whole struct was made to handle a the TILE_DOWNLOAD_CACHE side effects
-pub struct TileDownloader;
-impl TileDownloader {
-    fn clean_cache() {
-        unsafe {
-            #[allow(static_mut_refs)]
-            TILE_DOWNLOAD_CACHE
-                .get_mut()
-                .unwrap()
-                .retain(|_, v| v.last_download.elapsed().unwrap().as_secs() < 360);
+/// Tile downloads, the [`TileSource`] backed by the live wplace backend
+impl WplaceClient {
+    fn decode_and_remember(&self, tile: Tile, data: &[u8]) -> Result<TileImage, TileDownloadError> {
+        let image = Arc::new(self.decode_tile(tile, data)?.0);
+        self.tile_memory_cache
+            .lock()
+            .unwrap()
+            .insert(tile, image.clone());
+        Ok(image)
+    }
+
+    /// Validates the tile according to the client's [`tile_validation::TileValidation`]
+    ///
+    /// The second value tells whether the tile had to be repaired
+    pub(crate) fn decode_tile(
+        &self,
+        tile: Tile,
+        data: &[u8],
+    ) -> Result<(image::RgbaImage, bool), TileDownloadError> {
+        tile_validation::decode_tile(data, self.get_tile_validation())
+            .map_err(|source| TileDownloadError::InvalidTile { tile, source })
+    }
+
+    async fn request_tile(
+        &self,
+        url: &str,
+        cached: Option<&CachedTile>,
+    ) -> Result<(u32, curl::easy::Easy2<GenericResponse>), RequestError> {
+        let mut curl_client = self.new_request(url, 2_000_000)?;
+        if let Some(cached) = cached {
+            let mut headers = curl::easy::List::new();
+            for header in cached.conditional_headers() {
+                headers
+                    .append(&header)
+                    .map_err(|e| RequestError::from_curl(url, e))?;
+            }
+            curl_client
+                .http_headers(headers)
+                .map_err(|e| RequestError::from_curl(url, e))?;
         }
+        self.perform(url, curl_client).await
     }
 
-    fn get_from_cache(v: Tile) -> Option<DynamicImage> {
-        unsafe {
-            #[allow(static_mut_refs)]
-            TILE_DOWNLOAD_CACHE
-                .read()
-                .unwrap()
-                .get(&v)
-                .map(|v| v.image.clone())
+    /// Tiles served from a cache took 0 attempts
+    ///
+    /// The backend answers `404` for blank tiles, they come out fully transparent
+    pub async fn download_tile(
+        &self,
+        tile: Tile,
+    ) -> Result<Attempted<TileImage>, TileDownloadError> {
+        let cached = self.tile_memory_cache.lock().unwrap().get(tile);
+        if let Some(image) = cached {
+            return Ok(Attempted {
+                value: image,
+                attempts: 0,
+            });
         }
+
+        let Attempted {
+            value: (data, image),
+            attempts,
+        } = match self.download_tile_data(tile).await {
+            Ok(v) => v,
+            // Nobody painted on the tile yet
+            Err(TileDownloadError::RequestError(e)) if e.get_source().get_status() == Some(404) => {
+                let image = Arc::new(tile_validation::get_blank_tile());
+                self.tile_memory_cache
+                    .lock()
+                    .unwrap()
+                    .insert(tile, image.clone());
+                return Ok(Attempted {
+                    value: image,
+                    attempts: e.get_attempts(),
+                });
+            }
+            Err(e) => return Err(e),
+        };
+
+        let image = match image {
+            Some(image) => {
+                let image = Arc::new(image);
+                self.tile_memory_cache
+                    .lock()
+                    .unwrap()
+                    .insert(tile, image.clone());
+                image
+            }
+            None => self.decode_and_remember(tile, &data)?,
+        };
+        Ok(Attempted {
+            value: image,
+            attempts,
+        })
+    }
+
+    /// The tile as a valid PNG, going through the disk cache but not the memory one
+    ///
+    /// Valid tiles are kept as the backend encoded them, repaired ones are encoded again
+    ///
+    /// Tiles served from the disk cache took 0 attempts
+    pub async fn download_tile_png(
+        &self,
+        tile: Tile,
+    ) -> Result<Attempted<Vec<u8>>, TileDownloadError> {
+        Ok(self.download_tile_data(tile).await?.map(|(data, _)| data))
     }
 
-    pub(crate) fn download(tile_x: u16, tile_y: u16) -> Result<DynamicImage, image::ImageError> {
-        Self::clean_cache();
-        let tile = Tile::new(tile_x, tile_y);
+    /// Also returns the decoded tile when it had to be decoded to be validated, i.e. when it
+    /// didn't come from the disk cache
+    async fn download_tile_data(
+        &self,
+        tile: Tile,
+    ) -> Result<Attempted<(Vec<u8>, Option<image::RgbaImage>)>, TileDownloadError> {
+        let cached = match self.get_disk_cache() {
+            Some(disk_cache) => disk_cache.get(tile).await?,
+            None => None,
+        };
 
-        if let Some(image) = Self::get_from_cache(tile) {
-            return Ok(image);
+        if let (Some(disk_cache), Some(cached)) = (self.get_disk_cache(), &cached)
+            && cached.is_fresh(disk_cache.get_max_age())
+        {
+            return Ok(Attempted {
+                value: (cached.data.clone(), None),
+                attempts: 0,
+            });
         }
 
-        let mut curl_client = curl::easy::Easy2::new(GenericBytes(Vec::with_capacity(2_000_000)));
-        curl_client.url(&tile.url()).expect("Couldn't select url");
-        curl_client.perform().expect("Couldn't perform");
+        let url = self.get_tile_url(tile);
+        let Attempted {
+            value: (response_code, mut curl_client),
+            attempts,
+        } = self
+            .get_retry_policy()
+            .run(|| self.request_tile(&url, cached.as_ref()))
+            .await?;
+
+        if let (Some(disk_cache), Some(cached), 304) =
+            (self.get_disk_cache(), cached, response_code)
+        {
+            // The tile is good either way, it's only revalidated again sooner
+            let _ = disk_cache.refresh(tile, &cached).await;
+            return Ok(Attempted {
+                value: (cached.data, None),
+                attempts,
+            });
+        }
+
+        // Only tiles that pass validation get cached, anything else would be served back as
+        // fresh until it expires
+        let response = curl_client.get_mut();
+        let data = std::mem::take(&mut response.body);
+        let (image, repaired) = self.decode_tile(tile, &data)?;
+        let data = match repaired {
+            true => tile_validation::encode_tile(&image)?,
+            false => data,
+        };
+
+        // A tile that couldn't be cached was still downloaded fine
+        if let (Some(disk_cache), 200) = (self.get_disk_cache(), response_code) {
+            let _ = disk_cache
+                .store(
+                    tile,
+                    &data,
+                    response.get_header("ETag"),
+                    response.get_header("Last-Modified"),
+                )
+                .await;
+        }
+
+        Ok(Attempted {
+            value: (data, Some(image)),
+            attempts,
+        })
+    }
+}
+
+impl TileSource for WplaceClient {
+    async fn fetch_tile(&self, tile: Tile) -> Result<TileImage, TileSourceError> {
+        self.download_tile(tile)
+            .await
+            .map(Attempted::into_value)
+            .map_err(From::from)
+    }
 
-        image::load_from_memory_with_format(&curl_client.get_ref().0, image::ImageFormat::Png)
+    fn get_concurrency(&self) -> usize {
+        WplaceClient::get_concurrency(self)
     }
 }
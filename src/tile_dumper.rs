@@ -0,0 +1,288 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    tile_cache::{now_secs, write_atomically},
+    tile_downloader::{Tile, TileDownloadError},
+    tile_validation,
+    wplace_client::WplaceClient,
+};
+
+/// Downloads every tile of a rectangle into `{x}/{y}.png`, like the backend lays them out
+///
+/// Tiles are validated like the client validates them before being written, blank ones are
+/// written as transparent tiles so [`crate::tile_source::DirectoryTileSource`] can read the
+/// whole rectangle
+///
+/// Finished tiles are recorded in `manifest.jsonl`, so an interrupted dump resumes where it
+/// stopped instead of starting over
+#[derive(Clone, Debug)]
+pub struct TileDumper {
+    directory: PathBuf,
+    /// Top-left tile, included
+    first_tile: Tile,
+    /// Bottom-right tile, included
+    last_tile: Tile,
+    /// `None` uses the client's
+    concurrency: Option<usize>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TileDumperError {
+    #[error("I/O Error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("JSON Error: {0}")]
+    JSONError(#[from] serde_json::Error),
+    #[error("The manifest in the directory is for another dump: {0}")]
+    ManifestMismatch(String),
+}
+
+/// First line of the manifest
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct ManifestHeader {
+    first_tile: (u16, u16),
+    last_tile: (u16, u16),
+    season: String,
+}
+
+/// Every other line of the manifest, one per finished tile
+#[derive(Serialize, Deserialize, Debug)]
+struct ManifestEntry {
+    x: u16,
+    y: u16,
+    /// `None` for tiles the backend doesn't have, i.e. blank ones, written as transparent tiles
+    size: Option<u64>,
+    /// Seconds since the UNIX epoch
+    fetched_at: u64,
+}
+
+/// What a [`TileDumper::dump`] run did
+#[derive(Debug, Default)]
+pub struct DumpReport {
+    pub(crate) downloaded: usize,
+    /// Tiles a previous run already finished
+    pub(crate) skipped: usize,
+    /// Blank tiles, the backend answers `404` for them and they're written as transparent tiles
+    pub(crate) missing: Vec<Tile>,
+    /// Tiles that will be tried again by the next run
+    pub(crate) failed: Vec<(Tile, TileDownloadError)>,
+}
+
+impl DumpReport {
+    pub fn get_downloaded(&self) -> usize {
+        self.downloaded
+    }
+
+    pub fn get_skipped(&self) -> usize {
+        self.skipped
+    }
+
+    pub fn get_missing(&self) -> &[Tile] {
+        &self.missing
+    }
+
+    pub fn get_failed(&self) -> &[(Tile, TileDownloadError)] {
+        &self.failed
+    }
+
+    /// Whether every tile was either downloaded or found to be missing
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+enum TileOutcome {
+    Downloaded(u64),
+    Missing,
+    Failed(TileDownloadError),
+}
+
+impl TileDumper {
+    pub const MANIFEST_FILE_NAME: &str = "manifest.jsonl";
+
+    /// The corners can be given in any order
+    pub fn new<P: Into<PathBuf>>(directory: P, corner: Tile, opposite_corner: Tile) -> Self {
+        Self {
+            directory: directory.into(),
+            first_tile: Tile::new(
+                corner.x.min(opposite_corner.x),
+                corner.y.min(opposite_corner.y),
+            ),
+            last_tile: Tile::new(
+                corner.x.max(opposite_corner.x),
+                corner.y.max(opposite_corner.y),
+            ),
+            concurrency: None,
+        }
+    }
+
+    /// A `concurrency` of 0 is treated as 1
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency.max(1));
+        self
+    }
+
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn get_first_tile(&self) -> Tile {
+        self.first_tile
+    }
+
+    pub fn get_last_tile(&self) -> Tile {
+        self.last_tile
+    }
+
+    pub fn get_tile_path(&self, tile: Tile) -> PathBuf {
+        self.directory
+            .join(tile.x.to_string())
+            .join(format!("{}.png", tile.y))
+    }
+
+    pub fn get_manifest_path(&self) -> PathBuf {
+        self.directory.join(Self::MANIFEST_FILE_NAME)
+    }
+
+    /// Every tile of the rectangle, row by row
+    pub fn get_tiles(&self) -> impl Iterator<Item = Tile> + use<> {
+        itertools::iproduct!(
+            self.first_tile.y..=self.last_tile.y,
+            self.first_tile.x..=self.last_tile.x
+        )
+        .map(|(y, x)| Tile::new(x, y))
+    }
+
+    /// The tiles a previous run finished, creating the manifest if there isn't one yet
+    async fn read_manifest(&self, season: &str) -> Result<HashSet<Tile>, TileDumperError> {
+        let header = ManifestHeader {
+            first_tile: (self.first_tile.x, self.first_tile.y),
+            last_tile: (self.last_tile.x, self.last_tile.y),
+            season: season.to_string(),
+        };
+
+        let manifest = match tokio::fs::read_to_string(self.get_manifest_path()).await {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tokio::fs::create_dir_all(&self.directory).await?;
+                let mut line = serde_json::to_string(&header)?;
+                line.push('\n');
+                write_atomically(&self.get_manifest_path(), line.as_bytes()).await?;
+                return Ok(HashSet::new());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut lines = manifest.lines();
+        let found: ManifestHeader = serde_json::from_str(lines.next().unwrap_or_default())?;
+        if found != header {
+            return Err(TileDumperError::ManifestMismatch(format!(
+                "expected {header:?}, found {found:?}"
+            )));
+        }
+
+        // A run killed while writing leaves a truncated last line, that tile is fetched again,
+        // and the next entry must not be glued to it
+        if !manifest.ends_with('\n') {
+            tokio::fs::OpenOptions::new()
+                .append(true)
+                .open(self.get_manifest_path())
+                .await?
+                .write_all(b"\n")
+                .await?;
+        }
+
+        Ok(lines
+            .filter_map(|v| serde_json::from_str::<ManifestEntry>(v).ok())
+            .map(|v| Tile::new(v.x, v.y))
+            .collect())
+    }
+
+    async fn dump_tile(&self, client: &WplaceClient, tile: Tile) -> TileOutcome {
+        let (data, is_missing) = match client.download_tile_png(tile).await {
+            Ok(v) => (v.into_value(), false),
+            Err(TileDownloadError::RequestError(e)) if e.get_source().get_status() == Some(404) => {
+                match tile_validation::encode_tile(&tile_validation::get_blank_tile()) {
+                    Ok(v) => (v, true),
+                    Err(e) => return TileOutcome::Failed(e.into()),
+                }
+            }
+            Err(e) => return TileOutcome::Failed(e),
+        };
+
+        let path = self.get_tile_path(tile);
+        let written = async {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            write_atomically(&path, &data).await
+        };
+
+        match written.await {
+            Ok(()) if is_missing => TileOutcome::Missing,
+            Ok(()) => TileOutcome::Downloaded(data.len() as u64),
+            Err(e) => TileOutcome::Failed(e.into()),
+        }
+    }
+
+    /// Downloads every tile a previous run didn't finish, with at most `concurrency` in flight
+    ///
+    /// Tiles that fail don't stop the dump, they're listed in the report and retried by the
+    /// next run
+    pub async fn dump(&self, client: &WplaceClient) -> Result<DumpReport, TileDumperError> {
+        let finished = self.read_manifest(client.get_season()).await?;
+        let mut manifest = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(self.get_manifest_path())
+            .await?;
+
+        let mut report = DumpReport::default();
+        let tiles = self
+            .get_tiles()
+            .filter(|tile| {
+                let is_finished = finished.contains(tile);
+                report.skipped += is_finished as usize;
+                !is_finished
+            })
+            .collect::<Vec<_>>();
+
+        let mut outcomes = futures::stream::iter(tiles)
+            .map(|tile| async move { (tile, self.dump_tile(client, tile).await) })
+            .buffer_unordered(self.concurrency.unwrap_or(client.get_concurrency()));
+
+        while let Some((tile, outcome)) = outcomes.next().await {
+            let size = match outcome {
+                TileOutcome::Downloaded(size) => {
+                    report.downloaded += 1;
+                    Some(size)
+                }
+                TileOutcome::Missing => {
+                    report.missing.push(tile);
+                    None
+                }
+                TileOutcome::Failed(e) => {
+                    report.failed.push((tile, e));
+                    continue;
+                }
+            };
+
+            let mut line = serde_json::to_string(&ManifestEntry {
+                x: tile.x,
+                y: tile.y,
+                size,
+                fetched_at: now_secs(),
+            })?;
+            line.push('\n');
+            manifest.write_all(line.as_bytes()).await?;
+        }
+        manifest.flush().await?;
+
+        Ok(report)
+    }
+}
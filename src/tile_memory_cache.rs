@@ -0,0 +1,176 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use crate::{tile_downloader::Tile, tile_source::TileImage};
+
+/// Counters since the cache was created, plus its current occupancy
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TileCacheStats {
+    hits: u64,
+    /// Includes lookups of expired tiles
+    misses: u64,
+    /// Tiles dropped to stay within the byte budget
+    evictions: u64,
+    /// Tiles dropped because they outlived the TTL
+    expirations: u64,
+    entries: usize,
+    bytes: usize,
+}
+
+impl TileCacheStats {
+    pub fn get_hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn get_misses(&self) -> u64 {
+        self.misses
+    }
+
+    pub fn get_evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    pub fn get_expirations(&self) -> u64 {
+        self.expirations
+    }
+
+    pub fn get_entries(&self) -> usize {
+        self.entries
+    }
+
+    pub fn get_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// 0 when nothing was looked up yet
+    pub fn get_hit_ratio(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+struct TileMemoryCacheEntry {
+    image: TileImage,
+    inserted_at: Instant,
+    /// Key of this entry in `TileMemoryCache::recency`
+    last_used: u64,
+    size: usize,
+}
+
+/// Decoded tiles, evicted least recently used first once they exceed `max_bytes`
+pub(crate) struct TileMemoryCache {
+    max_bytes: usize,
+    ttl: Duration,
+    entries: HashMap<Tile, TileMemoryCacheEntry>,
+    /// Oldest use first
+    recency: BTreeMap<u64, Tile>,
+    /// Incremented on every use
+    clock: u64,
+    stats: TileCacheStats,
+}
+
+impl TileMemoryCache {
+    pub(crate) fn new(max_bytes: usize, ttl: Duration) -> Self {
+        Self {
+            max_bytes,
+            ttl,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            stats: TileCacheStats::default(),
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn remove(&mut self, tile: Tile) -> Option<TileMemoryCacheEntry> {
+        let entry = self.entries.remove(&tile)?;
+        self.recency.remove(&entry.last_used);
+        self.stats.bytes -= entry.size;
+        self.stats.entries -= 1;
+        Some(entry)
+    }
+
+    pub(crate) fn get(&mut self, tile: Tile) -> Option<TileImage> {
+        let Some(entry) = self.entries.get(&tile) else {
+            self.stats.misses += 1;
+            return None;
+        };
+
+        if entry.inserted_at.elapsed() >= self.ttl {
+            self.remove(tile);
+            self.stats.expirations += 1;
+            self.stats.misses += 1;
+            return None;
+        }
+
+        let tick = self.tick();
+        let entry = self.entries.get_mut(&tile)?;
+        self.recency.remove(&entry.last_used);
+        self.recency.insert(tick, tile);
+        entry.last_used = tick;
+        self.stats.hits += 1;
+        Some(entry.image.clone())
+    }
+
+    /// Tiles bigger than the whole budget aren't kept
+    pub(crate) fn insert(&mut self, tile: Tile, image: TileImage) {
+        self.remove(tile);
+
+        let size = image.as_raw().len();
+        if size > self.max_bytes {
+            return;
+        }
+
+        while self.stats.bytes + size > self.max_bytes {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.stats.bytes -= entry.size;
+                self.stats.entries -= 1;
+                self.stats.evictions += 1;
+            }
+        }
+
+        let tick = self.tick();
+        self.recency.insert(tick, tile);
+        self.entries.insert(
+            tile,
+            TileMemoryCacheEntry {
+                image,
+                inserted_at: Instant::now(),
+                last_used: tick,
+                size,
+            },
+        );
+        self.stats.bytes += size;
+        self.stats.entries += 1;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+        self.stats.bytes = 0;
+        self.stats.entries = 0;
+    }
+
+    pub(crate) fn get_stats(&self) -> TileCacheStats {
+        self.stats
+    }
+
+    pub(crate) fn get_max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    pub(crate) fn get_ttl(&self) -> Duration {
+        self.ttl
+    }
+}
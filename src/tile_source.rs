@@ -0,0 +1,166 @@
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+use futures::{StreamExt, TryStreamExt};
+use image::RgbaImage;
+
+use crate::{
+    global_pixel::GlobalPixelError,
+    tile_downloader::{Tile, TileDownloadError},
+    tile_validation::{self, TileValidation, TileValidationError},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum TileSourceError {
+    #[error("Image Error: {0}")]
+    ImageError(#[from] image::error::ImageError),
+    #[error("I/O Error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("TileDownload Error: {0}")]
+    TileDownloadError(#[from] TileDownloadError),
+    #[error("GlobalPixel Error: {0}")]
+    GlobalPixelError(#[from] GlobalPixelError),
+    #[error("Missing tile {}, {}", .0.x, .0.y)]
+    MissingTile(Tile),
+    #[error("Tile {}, {} is {width}x{height} instead of 1000x1000", .tile.x, .tile.y)]
+    InvalidTileSize { tile: Tile, width: u32, height: u32 },
+    #[error("Invalid tile {}, {}: {source}", .tile.x, .tile.y)]
+    InvalidTile {
+        tile: Tile,
+        source: TileValidationError,
+    },
+}
+
+/// A decoded tile, shared with whatever cache it came from
+pub type TileImage = Arc<RgbaImage>;
+
+/// Anything that can hand out the 1000x1000 tiles the canvas is made of
+pub trait TileSource: Sync {
+    fn fetch_tile(
+        &self,
+        tile: Tile,
+    ) -> impl Future<Output = Result<TileImage, TileSourceError>> + Send;
+
+    /// How many tiles `fetch_tiles` may request at the same time
+    fn get_concurrency(&self) -> usize {
+        1
+    }
+
+    /// Fetches every tile, with at most `get_concurrency` requests in flight
+    ///
+    /// Duplicate tiles are only fetched once
+    fn fetch_tiles<I: IntoIterator<Item = Tile>>(
+        &self,
+        tiles: I,
+    ) -> impl Future<Output = Result<HashMap<Tile, TileImage>, TileSourceError>> + Send {
+        let mut tiles = tiles.into_iter().collect::<Vec<_>>();
+        tiles.sort_unstable_by_key(|v| (v.y, v.x));
+        tiles.dedup();
+
+        futures::stream::iter(tiles)
+            .map(move |tile| async move { self.fetch_tile(tile).await.map(|image| (tile, image)) })
+            .buffer_unordered(self.get_concurrency().max(1))
+            .try_collect()
+    }
+}
+
+/// Reads tiles from a directory laid out like the backend, i.e. `{x}/{y}.png`
+///
+/// Tiles that aren't valid are rejected
+#[derive(Clone, Debug)]
+pub struct DirectoryTileSource {
+    root: PathBuf,
+    /// Whether absent files are blank tiles instead of [`TileSourceError::MissingTile`]
+    blank_missing_tiles: bool,
+}
+
+impl DirectoryTileSource {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            blank_missing_tiles: false,
+        }
+    }
+
+    /// For directories that only hold the painted tiles, like the backend does
+    pub fn with_blank_missing_tiles(mut self, blank_missing_tiles: bool) -> Self {
+        self.blank_missing_tiles = blank_missing_tiles;
+        self
+    }
+
+    pub fn get_root(&self) -> &std::path::Path {
+        &self.root
+    }
+
+    pub fn get_tile_path(&self, tile: Tile) -> PathBuf {
+        self.root
+            .join(tile.x.to_string())
+            .join(format!("{}.png", tile.y))
+    }
+}
+
+impl TileSource for DirectoryTileSource {
+    async fn fetch_tile(&self, tile: Tile) -> Result<TileImage, TileSourceError> {
+        let data = match tokio::fs::read(self.get_tile_path(tile)).await {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && self.blank_missing_tiles => {
+                return Ok(Arc::new(tile_validation::get_blank_tile()));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(TileSourceError::MissingTile(tile));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        tile_validation::decode_tile(&data, TileValidation::Reject)
+            .map(|(image, _)| Arc::new(image))
+            .map_err(|source| TileSourceError::InvalidTile { tile, source })
+    }
+
+    fn get_concurrency(&self) -> usize {
+        8
+    }
+}
+
+/// Keeps tiles in memory, mostly useful for tests
+#[derive(Clone, Default)]
+pub struct MemoryTileSource {
+    tiles: HashMap<Tile, TileImage>,
+}
+
+impl MemoryTileSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, tile: Tile, image: RgbaImage) -> Option<TileImage> {
+        self.tiles.insert(tile, Arc::new(image))
+    }
+
+    pub fn remove(&mut self, tile: Tile) -> Option<TileImage> {
+        self.tiles.remove(&tile)
+    }
+
+    pub fn get_tiles(&self) -> &HashMap<Tile, TileImage> {
+        &self.tiles
+    }
+}
+
+impl FromIterator<(Tile, RgbaImage)> for MemoryTileSource {
+    fn from_iter<T: IntoIterator<Item = (Tile, RgbaImage)>>(iter: T) -> Self {
+        Self {
+            tiles: iter
+                .into_iter()
+                .map(|(tile, image)| (tile, Arc::new(image)))
+                .collect(),
+        }
+    }
+}
+
+impl TileSource for MemoryTileSource {
+    async fn fetch_tile(&self, tile: Tile) -> Result<TileImage, TileSourceError> {
+        self.tiles
+            .get(&tile)
+            .cloned()
+            .ok_or(TileSourceError::MissingTile(tile))
+    }
+}
@@ -0,0 +1,131 @@
+use image::{Rgba, RgbaImage};
+
+use crate::{color::Color, global_pixel::GlobalPixel};
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const TILE_SIZE: u32 = GlobalPixel::TILE_SIZE;
+
+/// What to do with tiles that don't look like the backend's
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TileValidation {
+    /// Fail with a [`TileValidationError`]
+    #[default]
+    Reject,
+    /// Crop or pad to 1000x1000 with transparent pixels, round alpha to 0 or 255 and snap
+    /// colors to the closest one of the palette
+    ///
+    /// Data that isn't a PNG at all is still rejected
+    Repair,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TileValidationError {
+    #[error("Not a PNG, starts with {0:02X?}")]
+    InvalidSignature(Vec<u8>),
+    #[error("Image Error: {0}")]
+    ImageError(#[from] image::error::ImageError),
+    #[error("Tile is {width}x{height} instead of 1000x1000")]
+    InvalidSize { width: u32, height: u32 },
+    #[error("Alpha at {x}, {y} is {alpha} instead of 0 or 255")]
+    InvalidAlpha { x: u32, y: u32, alpha: u8 },
+    #[error("Invalid color at {x}, {y}: [{}, {}, {}, {}]", .rgba[0], .rgba[1], .rgba[2], .rgba[3])]
+    InvalidColor { x: u32, y: u32, rgba: [u8; 4] },
+}
+
+/// The palette color closest to `rgba`, ignoring its alpha
+fn get_closest_color(rgba: [u8; 4]) -> Color {
+    let distance = |color: Color| {
+        let [r, g, b, _] = <[u8; 4]>::from(color);
+        [(r, rgba[0]), (g, rgba[1]), (b, rgba[2])]
+            .iter()
+            .map(|(a, b)| (*a as i32 - *b as i32).pow(2))
+            .sum::<i32>()
+    };
+
+    (0..u8::MAX)
+        .map_while(|v| Color::try_from(v).ok())
+        .filter(|v| *v != Color::Transparent)
+        .min_by_key(|v| distance(*v))
+        .unwrap_or(Color::Black)
+}
+
+/// Checks the size, alpha and colors of a decoded tile
+pub fn check_tile(image: &RgbaImage) -> Result<(), TileValidationError> {
+    if image.dimensions() != (TILE_SIZE, TILE_SIZE) {
+        return Err(TileValidationError::InvalidSize {
+            width: image.width(),
+            height: image.height(),
+        });
+    }
+
+    for (x, y, pixel) in image.enumerate_pixels() {
+        match pixel.0 {
+            [_, _, _, 0] => continue,
+            [_, _, _, 255] => {
+                if Color::try_from(pixel.0).is_err() {
+                    return Err(TileValidationError::InvalidColor {
+                        x,
+                        y,
+                        rgba: pixel.0,
+                    });
+                }
+            }
+            [_, _, _, alpha] => return Err(TileValidationError::InvalidAlpha { x, y, alpha }),
+        }
+    }
+
+    Ok(())
+}
+
+/// A fully transparent tile, what the backend's `404` stands for
+pub fn get_blank_tile() -> RgbaImage {
+    RgbaImage::new(TILE_SIZE, TILE_SIZE)
+}
+
+/// Encodes a tile as a PNG, like the backend serves them
+pub(crate) fn encode_tile(image: &RgbaImage) -> Result<Vec<u8>, image::error::ImageError> {
+    let mut encoded = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut encoded, image::ImageFormat::Png)?;
+    Ok(encoded.into_inner())
+}
+
+/// Makes any image a valid tile, see [`TileValidation::Repair`]
+pub fn repair_tile(image: &RgbaImage) -> RgbaImage {
+    let mut out = RgbaImage::new(TILE_SIZE, TILE_SIZE);
+
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if x >= TILE_SIZE || y >= TILE_SIZE || pixel.0[3] < 128 {
+            continue;
+        }
+
+        let repaired = match Color::try_from([pixel.0[0], pixel.0[1], pixel.0[2], 255]) {
+            Ok(color) => color,
+            Err(_) => get_closest_color(pixel.0),
+        };
+        out.put_pixel(x, y, Rgba(repaired.into()));
+    }
+
+    out
+}
+
+/// Decodes a downloaded tile, making sure it is one
+///
+/// The second value tells whether the tile had to be repaired
+pub fn decode_tile(
+    data: &[u8],
+    validation: TileValidation,
+) -> Result<(RgbaImage, bool), TileValidationError> {
+    if !data.starts_with(&PNG_SIGNATURE) {
+        return Err(TileValidationError::InvalidSignature(
+            data[..data.len().min(PNG_SIGNATURE.len())].to_vec(),
+        ));
+    }
+
+    let image = image::load_from_memory_with_format(data, image::ImageFormat::Png)?.into_rgba8();
+
+    match (check_tile(&image), validation) {
+        (Ok(()), _) => Ok((image, false)),
+        (Err(_), TileValidation::Repair) => Ok((repair_tile(&image), true)),
+        (Err(e), TileValidation::Reject) => Err(e),
+    }
+}
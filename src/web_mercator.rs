@@ -0,0 +1,98 @@
+use std::f64::consts::PI;
+
+use crate::{global_pixel::GlobalPixel, map_coords::MapCoords};
+
+/// A point in EPSG:3857 meters, the projection the map of wplace.live uses
+///
+/// `x` grows east of the prime meridian and `y` north of the equator, the canvas goes from
+/// [`Self::CANVAS_EDGE`] west and north to the same distance east and south
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct WebMercatorCoords {
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+}
+
+impl WebMercatorCoords {
+    /// How far the edges of the canvas are from its center, in meters
+    pub const CANVAS_EDGE: f64 = PI * MapCoords::EARTH_RADIUS;
+
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    pub fn get_x(&self) -> f64 {
+        self.x
+    }
+
+    pub fn get_y(&self) -> f64 {
+        self.y
+    }
+
+    /// Points past ±85.05° of latitude end up past the edges of the canvas, the poles are
+    /// infinitely far
+    pub fn from_map_coords(map_coords: &MapCoords) -> Self {
+        Self {
+            x: map_coords.get_lng() / 180f64 * Self::CANVAS_EDGE,
+            y: MapCoords::EARTH_RADIUS
+                * (PI / 4f64 + map_coords.get_lat().to_radians() / 2f64)
+                    .tan()
+                    .ln(),
+        }
+    }
+
+    /// Longitudes aren't brought back between -180 and 180
+    pub fn to_map_coords(&self, zoom: f32) -> MapCoords {
+        MapCoords::new(
+            (2f64 * (self.y / MapCoords::EARTH_RADIUS).exp().atan() - PI / 2f64).to_degrees(),
+            self.x / Self::CANVAS_EDGE * 180f64,
+            zoom,
+        )
+    }
+
+    /// The top-left corner of the pixel
+    pub fn from_global_pixel(pixel: GlobalPixel) -> Self {
+        Self::from_canvas_position(pixel.get_x() as f64, pixel.get_y() as f64)
+    }
+
+    /// `x` and `y` are in pixels from the top-left corner of the canvas
+    pub(crate) fn from_canvas_position(x: f64, y: f64) -> Self {
+        let size = GlobalPixel::CANVAS_SIZE as f64;
+        Self {
+            x: (2f64 * x / size - 1f64) * Self::CANVAS_EDGE,
+            y: (1f64 - 2f64 * y / size) * Self::CANVAS_EDGE,
+        }
+    }
+
+    /// Where this point is in pixels from the top-left corner of the canvas
+    pub(crate) fn get_canvas_position(&self) -> (f64, f64) {
+        let size = GlobalPixel::CANVAS_SIZE as f64;
+        (
+            (self.x / Self::CANVAS_EDGE + 1f64) * size / 2f64,
+            (1f64 - self.y / Self::CANVAS_EDGE) * size / 2f64,
+        )
+    }
+}
+
+impl From<&MapCoords> for WebMercatorCoords {
+    fn from(value: &MapCoords) -> Self {
+        Self::from_map_coords(value)
+    }
+}
+
+impl From<GlobalPixel> for WebMercatorCoords {
+    fn from(value: GlobalPixel) -> Self {
+        Self::from_global_pixel(value)
+    }
+}
+
+impl From<WebMercatorCoords> for geo_types::Point<f64> {
+    fn from(value: WebMercatorCoords) -> Self {
+        geo_types::Point::new(value.x, value.y)
+    }
+}
+
+impl From<geo_types::Point<f64>> for WebMercatorCoords {
+    fn from(value: geo_types::Point<f64>) -> Self {
+        Self::new(value.x(), value.y())
+    }
+}
@@ -0,0 +1,418 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_curl::{Actor, CurlActor};
+use curl::easy::Easy2;
+
+use crate::{
+    GenericResponse,
+    map_coords::MapCoords,
+    nominatim_data::NominatimCache,
+    pixel_info::PixelInfoCache,
+    rate_limiter::RateLimiter,
+    request::{self, RequestError},
+    retry_policy::RetryPolicy,
+    tile_cache::DiskTileCache,
+    tile_coords::TileCoords,
+    tile_downloader::Tile,
+    tile_memory_cache::{TileCacheStats, TileMemoryCache},
+    tile_validation::TileValidation,
+};
+
+/// Encodes everything but RFC 3986's unreserved characters
+fn percent_encode(v: &str) -> String {
+    v.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Owns everything needed to talk to wplace and Nominatim: caches, rate limits and settings
+///
+/// Clients are independent from each other, unless they share a [`RateLimiter`]
+pub struct WplaceClient {
+    /// Every request is handed to this actor, which runs the curl transfers on its own thread
+    curl_actor: CurlActor<GenericResponse>,
+    rate_limiter: Arc<RateLimiter>,
+    retry_policy: RetryPolicy,
+    /// How many tiles may be in flight at the same time
+    concurrency: usize,
+    /// Where tiles are persisted between runs, if anywhere
+    disk_cache: Option<DiskTileCache>,
+    tile_validation: TileValidation,
+    tile_base_url: String,
+    backend_url: String,
+    season: String,
+    nominatim_endpoint: String,
+    user_agent: String,
+    contact_email: Option<String>,
+    proxy: Option<String>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    pub(crate) tile_memory_cache: Mutex<TileMemoryCache>,
+    pub(crate) nominatim_cache: Mutex<NominatimCache>,
+    /// How long pixel info lookups are kept
+    pixel_info_ttl: Duration,
+    pub(crate) pixel_info_cache: Mutex<PixelInfoCache>,
+}
+
+/// Configures a [`WplaceClient`], every setting has a default pointing at production
+#[derive(Clone)]
+pub struct WplaceClientBuilder {
+    rate_limiter: Option<Arc<RateLimiter>>,
+    retry_policy: RetryPolicy,
+    concurrency: usize,
+    disk_cache: Option<DiskTileCache>,
+    tile_validation: TileValidation,
+    memory_cache_size: usize,
+    memory_cache_ttl: Duration,
+    pixel_info_ttl: Duration,
+    tile_base_url: String,
+    backend_url: String,
+    season: String,
+    nominatim_endpoint: String,
+    user_agent: String,
+    contact_email: Option<String>,
+    proxy: Option<String>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+}
+
+impl Default for WplaceClientBuilder {
+    fn default() -> Self {
+        Self {
+            rate_limiter: None,
+            retry_policy: RetryPolicy::default(),
+            concurrency: WplaceClient::DEFAULT_CONCURRENCY,
+            disk_cache: None,
+            tile_validation: TileValidation::default(),
+            memory_cache_size: WplaceClient::DEFAULT_MEMORY_CACHE_SIZE,
+            memory_cache_ttl: WplaceClient::DEFAULT_MEMORY_CACHE_TTL,
+            pixel_info_ttl: WplaceClient::DEFAULT_PIXEL_INFO_TTL,
+            tile_base_url: WplaceClient::DEFAULT_TILE_BASE_URL.to_string(),
+            backend_url: WplaceClient::DEFAULT_BACKEND_URL.to_string(),
+            season: WplaceClient::DEFAULT_SEASON.to_string(),
+            nominatim_endpoint: WplaceClient::DEFAULT_NOMINATIM_ENDPOINT.to_string(),
+            user_agent: WplaceClient::DEFAULT_USER_AGENT.to_string(),
+            contact_email: None,
+            proxy: None,
+            connect_timeout: Some(WplaceClient::DEFAULT_CONNECT_TIMEOUT),
+            timeout: Some(WplaceClient::DEFAULT_TIMEOUT),
+        }
+    }
+}
+
+impl WplaceClientBuilder {
+    /// Pass the same limiter to several clients to make them share their budgets
+    ///
+    /// By default every client gets its own, with the default budgets for the configured hosts
+    pub fn rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// A `concurrency` of 0 is treated as 1
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn disk_cache(mut self, disk_cache: DiskTileCache) -> Self {
+        self.disk_cache = Some(disk_cache);
+        self
+    }
+
+    /// What to do with downloaded tiles that aren't valid, they're rejected by default
+    pub fn tile_validation(mut self, tile_validation: TileValidation) -> Self {
+        self.tile_validation = tile_validation;
+        self
+    }
+
+    /// How many bytes of decoded tiles are kept in memory, each tile takes 4 MB
+    pub fn memory_cache_size(mut self, memory_cache_size: usize) -> Self {
+        self.memory_cache_size = memory_cache_size;
+        self
+    }
+
+    /// How long decoded tiles are kept in memory
+    pub fn memory_cache_ttl(mut self, memory_cache_ttl: Duration) -> Self {
+        self.memory_cache_ttl = memory_cache_ttl;
+        self
+    }
+
+    /// Where tiles are served from, tiles are then fetched from `{base}/{season}/tiles/{x}/{y}.png`
+    pub fn tile_base_url(mut self, tile_base_url: impl ToString) -> Self {
+        self.tile_base_url = tile_base_url.to_string().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// How long pixel info lookups are kept in memory
+    pub fn pixel_info_ttl(mut self, pixel_info_ttl: Duration) -> Self {
+        self.pixel_info_ttl = pixel_info_ttl;
+        self
+    }
+
+    /// Where the API is served from, pixel info is then fetched from
+    /// `{base}/{season}/pixel/{tile x}/{tile y}?x={x}&y={y}`
+    pub fn backend_url(mut self, backend_url: impl ToString) -> Self {
+        self.backend_url = backend_url.to_string().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// The canvas season, e.g. `s0`
+    pub fn season(mut self, season: impl ToString) -> Self {
+        self.season = season.to_string();
+        self
+    }
+
+    /// The Nominatim reverse geocoding endpoint, e.g. `https://nominatim.openstreetmap.org/reverse`
+    pub fn nominatim_endpoint(mut self, nominatim_endpoint: impl ToString) -> Self {
+        self.nominatim_endpoint = nominatim_endpoint.to_string();
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl ToString) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    /// Nominatim's usage policy asks for a way to contact heavy users
+    ///
+    /// It is added to the user agent and sent to Nominatim as `email`
+    pub fn contact_email(mut self, contact_email: impl ToString) -> Self {
+        self.contact_email = Some(contact_email.to_string());
+        self
+    }
+
+    /// Any proxy URL curl understands, e.g. `socks5h://localhost:9050`
+    pub fn proxy(mut self, proxy: impl ToString) -> Self {
+        self.proxy = Some(proxy.to_string());
+        self
+    }
+
+    /// `None` waits for as long as the system allows
+    pub fn connect_timeout(mut self, connect_timeout: Option<Duration>) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// Bounds a whole request, `None` waits forever
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn build(self) -> WplaceClient {
+        let rate_limiter = self.rate_limiter.unwrap_or_else(|| {
+            Arc::new(
+                RateLimiter::new()
+                    .with_limit(
+                        RateLimiter::get_host(&self.backend_url),
+                        RateLimiter::DEFAULT_TILE_BACKEND_LIMIT,
+                    )
+                    .with_limit(
+                        RateLimiter::get_host(&self.tile_base_url),
+                        RateLimiter::DEFAULT_TILE_BACKEND_LIMIT,
+                    )
+                    .with_limit(
+                        RateLimiter::get_host(&self.nominatim_endpoint),
+                        RateLimiter::DEFAULT_NOMINATIM_LIMIT,
+                    ),
+            )
+        });
+
+        WplaceClient {
+            curl_actor: CurlActor::new(),
+            rate_limiter,
+            retry_policy: self.retry_policy,
+            concurrency: self.concurrency,
+            disk_cache: self.disk_cache,
+            tile_validation: self.tile_validation,
+            tile_base_url: self.tile_base_url,
+            backend_url: self.backend_url,
+            season: self.season,
+            nominatim_endpoint: self.nominatim_endpoint,
+            user_agent: self.user_agent,
+            contact_email: self.contact_email,
+            proxy: self.proxy,
+            connect_timeout: self.connect_timeout,
+            timeout: self.timeout,
+            tile_memory_cache: Mutex::new(TileMemoryCache::new(
+                self.memory_cache_size,
+                self.memory_cache_ttl,
+            )),
+            nominatim_cache: Mutex::new(NominatimCache::new()),
+            pixel_info_ttl: self.pixel_info_ttl,
+            pixel_info_cache: Mutex::new(PixelInfoCache::new()),
+        }
+    }
+}
+
+impl Default for WplaceClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WplaceClient {
+    pub const DEFAULT_CONCURRENCY: usize = 8;
+    pub const DEFAULT_MEMORY_CACHE_SIZE: usize = 256 * 1024 * 1024;
+    pub const DEFAULT_MEMORY_CACHE_TTL: Duration = Duration::from_secs(360);
+    pub const DEFAULT_PIXEL_INFO_TTL: Duration = Duration::from_secs(60);
+    pub const DEFAULT_TILE_BASE_URL: &str = "https://backend.wplace.live/files";
+    pub const DEFAULT_BACKEND_URL: &str = "https://backend.wplace.live";
+    pub const DEFAULT_SEASON: &str = "s0";
+    pub const DEFAULT_NOMINATIM_ENDPOINT: &str = "https://nominatim.openstreetmap.org/reverse";
+    pub const DEFAULT_USER_AGENT: &str =
+        concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+    pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// A client with every setting left to its default
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> WplaceClientBuilder {
+        WplaceClientBuilder::default()
+    }
+
+    pub fn get_rate_limiter(&self) -> &Arc<RateLimiter> {
+        &self.rate_limiter
+    }
+
+    pub fn get_retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub fn get_concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn get_disk_cache(&self) -> Option<&DiskTileCache> {
+        self.disk_cache.as_ref()
+    }
+
+    pub fn get_tile_validation(&self) -> TileValidation {
+        self.tile_validation
+    }
+
+    pub fn get_memory_cache_stats(&self) -> TileCacheStats {
+        self.tile_memory_cache.lock().unwrap().get_stats()
+    }
+
+    pub fn get_memory_cache_size(&self) -> usize {
+        self.tile_memory_cache.lock().unwrap().get_max_bytes()
+    }
+
+    pub fn get_memory_cache_ttl(&self) -> Duration {
+        self.tile_memory_cache.lock().unwrap().get_ttl()
+    }
+
+    /// Drops every decoded tile, statistics are kept
+    pub fn clear_memory_cache(&self) {
+        self.tile_memory_cache.lock().unwrap().clear();
+    }
+
+    pub fn get_season(&self) -> &str {
+        &self.season
+    }
+
+    pub fn get_tile_url(&self, tile: Tile) -> String {
+        format!(
+            "{}/{}/tiles/{}/{}.png",
+            self.tile_base_url, self.season, tile.x, tile.y
+        )
+    }
+
+    pub fn get_pixel_info_url(&self, coords: &TileCoords) -> String {
+        format!(
+            "{}/{}/pixel/{}/{}?x={}&y={}",
+            self.backend_url, self.season, coords.tile_x, coords.tile_y, coords.x, coords.y
+        )
+    }
+
+    pub fn get_pixel_info_ttl(&self) -> Duration {
+        self.pixel_info_ttl
+    }
+
+    pub fn get_nominatim_url(&self, v: &MapCoords) -> String {
+        let url = v.get_nominatim_link_for(&self.nominatim_endpoint);
+        match &self.contact_email {
+            Some(email) => format!("{url}&email={}", percent_encode(email)),
+            None => url,
+        }
+    }
+
+    pub fn get_user_agent(&self) -> String {
+        match &self.contact_email {
+            Some(email) => format!("{} ({email})", self.user_agent),
+            None => self.user_agent.clone(),
+        }
+    }
+
+    /// A request to `url` with the client's user agent, proxy and timeouts
+    pub(crate) fn new_request(
+        &self,
+        url: &str,
+        capacity: usize,
+    ) -> Result<Easy2<GenericResponse>, RequestError> {
+        let mut curl_client = Easy2::new(GenericResponse::with_capacity(capacity));
+        let map_err = |e| RequestError::from_curl(url, e);
+
+        curl_client.url(url).map_err(map_err)?;
+        curl_client
+            .useragent(&self.get_user_agent())
+            .map_err(map_err)?;
+        if let Some(proxy) = &self.proxy {
+            curl_client.proxy(proxy).map_err(map_err)?;
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            curl_client
+                .connect_timeout(connect_timeout)
+                .map_err(map_err)?;
+        }
+        if let Some(timeout) = self.timeout {
+            curl_client.timeout(timeout).map_err(map_err)?;
+        }
+
+        Ok(curl_client)
+    }
+
+    /// Waits for the host's [`RateLimiter`] budget before sending the request
+    pub(crate) async fn perform(
+        &self,
+        url: &str,
+        curl_client: Easy2<GenericResponse>,
+    ) -> Result<(u32, Easy2<GenericResponse>), RequestError> {
+        self.rate_limiter.acquire(RateLimiter::get_host(url)).await;
+
+        let curl_client = self
+            .curl_actor
+            .send_request(curl_client)
+            .await
+            .map_err(|e| match e {
+                async_curl::Error::Curl(e) => RequestError::from_curl(url, e),
+                async_curl::Error::Multi(e) => RequestError::CurlMultiError {
+                    url: url.to_string(),
+                    source: e,
+                },
+                async_curl::Error::TokioRecv(_) | async_curl::Error::TokioSend(_) => {
+                    RequestError::ActorStopped
+                }
+            })?;
+
+        Ok((request::check_response(url, &curl_client)?, curl_client))
+    }
+}
@@ -0,0 +1,145 @@
+use image::{Rgba, RgbaImage};
+use wplace_core_library::{
+    canvas_rect::{CanvasRect, CanvasRectError},
+    color::Color,
+    global_pixel::GlobalPixel,
+    image_comparison::ImageComparison,
+    image_data::ImageData,
+    tile_coords::TileCoords,
+    tile_downloader::Tile,
+    tile_source::MemoryTileSource,
+};
+
+const LAST: u32 = GlobalPixel::CANVAS_SIZE - 1;
+
+fn rect(x: u32, y: u32, width: u32, height: u32) -> CanvasRect {
+    CanvasRect::new(GlobalPixel::new(x, y).unwrap(), width, height).unwrap()
+}
+
+#[test]
+fn rejects_empty_and_overhanging_rects() {
+    let corner = GlobalPixel::new(10, 10).unwrap();
+    assert_eq!(
+        CanvasRect::new(corner, 0, 5),
+        Err(CanvasRectError::InvalidWidth)
+    );
+    assert_eq!(
+        CanvasRect::new(corner, 5, 0),
+        Err(CanvasRectError::InvalidHeight)
+    );
+    for (width, height) in [
+        (GlobalPixel::CANVAS_SIZE + 1, 1),
+        (1, GlobalPixel::CANVAS_SIZE),
+    ] {
+        assert!(matches!(
+            CanvasRect::new(corner, width, height),
+            Err(CanvasRectError::GlobalPixelError(_))
+        ));
+    }
+}
+
+#[test]
+fn intersects_unites_and_contains() {
+    let a = rect(0, 0, 10, 10);
+    let b = rect(5, 8, 10, 10);
+
+    assert_eq!(a.intersection(&b), Some(rect(5, 8, 5, 2)));
+    assert_eq!(a.union(&b), rect(0, 0, 15, 18));
+    assert!(a.union(&b).contains(&a) && a.union(&b).contains(&b));
+    assert!(!a.contains(&b));
+
+    let c = rect(10, 0, 1, 1);
+    assert_eq!(a.intersection(&c), None);
+    assert!(!a.intersects(&c));
+    assert!(a.expand(1).intersects(&c));
+}
+
+#[test]
+fn expands_up_to_the_canvas_edges() {
+    assert_eq!(rect(2, 3, 4, 5).expand(10), rect(LAST - 7, 0, 24, 18));
+    assert_eq!(
+        rect(LAST, LAST, 1, 1).expand(2),
+        rect(LAST - 2, LAST - 2, 5, 3)
+    );
+}
+
+#[test]
+fn splits_into_tile_rects() {
+    let tile_rects = rect(990, 1995, 1020, 10)
+        .get_tile_rects()
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tile_rects,
+        [
+            (Tile::new(0, 1), rect(990, 1995, 10, 5)),
+            (Tile::new(1, 1), rect(1000, 1995, 1000, 5)),
+            (Tile::new(2, 1), rect(2000, 1995, 10, 5)),
+            (Tile::new(0, 2), rect(990, 2000, 10, 5)),
+            (Tile::new(1, 2), rect(1000, 2000, 1000, 5)),
+            (Tile::new(2, 2), rect(2000, 2000, 10, 5)),
+        ]
+    );
+}
+
+#[test]
+fn goes_around_the_antimeridian() {
+    let a = rect(LAST - 4, 0, 10, 10);
+    assert!(a.is_wrapping());
+    assert_eq!(a.get_bottom_right_corner(), GlobalPixel::new(4, 9).unwrap());
+    assert!(a.contains_pixel(GlobalPixel::new(0, 0).unwrap()));
+    assert!(!a.contains_pixel(GlobalPixel::new(5, 0).unwrap()));
+
+    let b = rect(2, 5, 10, 10);
+    assert_eq!(a.intersection(&b), Some(rect(2, 5, 3, 5)));
+    assert_eq!(a.union(&b), rect(LAST - 4, 0, 17, 15));
+    assert!(a.contains(&rect(LAST, 0, 3, 3)));
+
+    let whole_width = rect(500, 0, GlobalPixel::CANVAS_SIZE, 1);
+    assert_eq!(whole_width.intersection(&a), Some(rect(LAST - 4, 0, 10, 1)));
+    assert_eq!(whole_width.get_tiles().count(), 2048);
+    assert_eq!(whole_width.get_tile_rects().count(), 2049);
+
+    assert_eq!(
+        a.get_tile_rects().collect::<Vec<_>>(),
+        [
+            (Tile::new(2047, 0), rect(LAST - 4, 0, 5, 10)),
+            (Tile::new(0, 0), rect(0, 0, 5, 10)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn loads_and_compares_areas_across_the_antimeridian() {
+    let mut source = MemoryTileSource::new();
+    for (tile, color) in [
+        (Tile::new(2047, 5), Color::Red),
+        (Tile::new(0, 5), Color::Blue),
+    ] {
+        source.insert(tile, RgbaImage::from_pixel(1000, 1000, Rgba(color.into())));
+    }
+
+    let top_left_corner = TileCoords::new(2047, 5, 995, 0);
+    let current = ImageData::from_site_coords(&source, &top_left_corner, 10, 1)
+        .await
+        .unwrap();
+    assert_eq!(current.get_color_counts()[&Color::Red], 5);
+    assert_eq!(current.get_color_counts()[&Color::Blue], 5);
+
+    let template = ImageData::new(RgbaImage::from_pixel(10, 1, Rgba(Color::Red.into()))).unwrap();
+    let comparison = ImageComparison::compare_with_source(&template, &top_left_corner, &source)
+        .await
+        .unwrap();
+    let top_left_corner = GlobalPixel::try_from(top_left_corner).unwrap();
+    assert_eq!(
+        comparison.get_different_coords(top_left_corner).next(),
+        Some(TileCoords::new(0, 5, 0, 0))
+    );
+    assert_eq!(
+        comparison.get_difference_rect(top_left_corner),
+        Some(rect(0, 5000, 5, 1))
+    );
+    assert_eq!(
+        TileCoords::new(2047, 5, 999, 0).sum_x_y(1, 0),
+        Ok(TileCoords::new(0, 5, 0, 0))
+    );
+}
@@ -0,0 +1,638 @@
+use std::time::{Duration, Instant};
+
+use image::RgbaImage;
+use wplace_core_library::{
+    canvas_rect::CanvasRect,
+    color::Color,
+    fake_backend::{FakeBackend, FakeResponse},
+    global_pixel::GlobalPixel,
+    image_comparison::ImageComparison,
+    image_data::{ImageData, ImageDataError},
+    map_coords::MapCoords,
+    nominatim_data::NominatimData,
+    retry_policy::RetryPolicy,
+    template_data::{LocationData, TemplateData},
+    tile_cache::DiskTileCache,
+    tile_coords::TileCoords,
+    tile_downloader::{Tile, TileDownloadError},
+    tile_dumper::TileDumper,
+    tile_source::{DirectoryTileSource, TileSourceError},
+    tile_validation::{TileValidation, TileValidationError},
+};
+
+#[tokio::test]
+async fn downloads_tiles_and_serves_them_from_memory() {
+    let backend = FakeBackend::start().await.unwrap();
+    backend.fill_tile(Tile::new(10, 20), Color::Red);
+    let client = backend.client_builder().build();
+
+    let tile = client.download_tile(Tile::new(10, 20)).await.unwrap();
+    assert_eq!(tile.get_attempts(), 1);
+    assert_eq!(
+        tile.get_value().get_pixel(500, 500).0,
+        <[u8; 4]>::from(Color::Red)
+    );
+
+    let tile = client.download_tile(Tile::new(10, 20)).await.unwrap();
+    assert_eq!(tile.get_attempts(), 0);
+    assert_eq!(backend.get_request_count("/files/s0/tiles/10/20.png"), 1);
+}
+
+#[tokio::test]
+async fn evicts_and_expires_tiles_in_memory() {
+    const TILE_BYTES: usize = 1000 * 1000 * 4;
+    let backend = FakeBackend::start().await.unwrap();
+    for x in 0..3 {
+        backend.fill_tile(Tile::new(x, 0), Color::Red);
+    }
+    let client = backend
+        .client_builder()
+        .memory_cache_size(TILE_BYTES * 2 + 100)
+        .build();
+
+    client.download_tile(Tile::new(0, 0)).await.unwrap();
+    client.download_tile(Tile::new(1, 0)).await.unwrap();
+    assert_eq!(
+        client
+            .download_tile(Tile::new(0, 0))
+            .await
+            .unwrap()
+            .get_attempts(),
+        0
+    );
+    // Evicts 1, 0 is more recently used
+    client.download_tile(Tile::new(2, 0)).await.unwrap();
+
+    let stats = client.get_memory_cache_stats();
+    assert_eq!(stats.get_hits(), 1);
+    assert_eq!(stats.get_misses(), 3);
+    assert_eq!(stats.get_evictions(), 1);
+    assert_eq!(stats.get_expirations(), 0);
+    assert_eq!(stats.get_entries(), 2);
+    assert_eq!(stats.get_bytes(), TILE_BYTES * 2);
+    assert_eq!(stats.get_hit_ratio(), 0.25);
+
+    client.download_tile(Tile::new(0, 0)).await.unwrap();
+    assert_eq!(backend.get_request_count("/files/s0/tiles/0/0.png"), 1);
+    client.download_tile(Tile::new(1, 0)).await.unwrap();
+    assert_eq!(backend.get_request_count("/files/s0/tiles/1/0.png"), 2);
+    assert_eq!(client.get_memory_cache_stats().get_evictions(), 2);
+
+    client.clear_memory_cache();
+    let stats = client.get_memory_cache_stats();
+    assert_eq!((stats.get_entries(), stats.get_bytes()), (0, 0));
+    assert_eq!(stats.get_hits(), 2);
+
+    // Everything is expired as soon as it's stored
+    let client = backend
+        .client_builder()
+        .memory_cache_ttl(Duration::ZERO)
+        .build();
+    client.download_tile(Tile::new(0, 0)).await.unwrap();
+    client.download_tile(Tile::new(0, 0)).await.unwrap();
+    assert_eq!(backend.get_request_count("/files/s0/tiles/0/0.png"), 3);
+
+    let stats = client.get_memory_cache_stats();
+    assert_eq!(stats.get_hits(), 0);
+    assert_eq!(stats.get_misses(), 2);
+    assert_eq!(stats.get_expirations(), 1);
+    assert_eq!(stats.get_entries(), 1);
+}
+
+#[tokio::test]
+async fn retries_rate_limited_requests() {
+    let backend = FakeBackend::start().await.unwrap();
+    backend.fill_tile(Tile::new(0, 0), Color::Black);
+    backend.push_response(
+        "/files",
+        FakeResponse::TooManyRequests {
+            retry_after: Some(0),
+        },
+    );
+    backend.push_response(
+        "/files",
+        FakeResponse::Status {
+            status: 503,
+            body: String::from("Down"),
+        },
+    );
+    let client = backend.client_builder().build();
+
+    let tile = client.download_tile(Tile::new(0, 0)).await.unwrap();
+    assert_eq!(tile.get_attempts(), 3);
+}
+
+#[tokio::test]
+async fn reports_attempts_when_giving_up() {
+    let backend = FakeBackend::start().await.unwrap();
+    let client = backend
+        .client_builder()
+        .retry_policy(RetryPolicy::new(2).with_initial_backoff(Duration::from_millis(1)))
+        .build();
+    for _ in 0..2 {
+        backend.push_response(
+            "/files",
+            FakeResponse::TooManyRequests { retry_after: None },
+        );
+    }
+
+    match client.download_tile(Tile::new(0, 0)).await {
+        Err(TileDownloadError::RequestError(e)) => {
+            assert_eq!(e.get_attempts(), 2);
+            assert_eq!(e.get_source().get_status(), Some(429));
+        }
+        _ => panic!("Expected a request error"),
+    }
+}
+
+#[tokio::test]
+async fn gives_up_when_asked_to_retry_too_late() {
+    let backend = FakeBackend::start().await.unwrap();
+    backend.fill_tile(Tile::new(0, 0), Color::Black);
+    backend.push_response(
+        "/files",
+        FakeResponse::TooManyRequests {
+            retry_after: Some(86400),
+        },
+    );
+    let client = backend.client_builder().build();
+
+    let started = std::time::Instant::now();
+    match client.download_tile(Tile::new(0, 0)).await {
+        Err(TileDownloadError::RequestError(e)) => {
+            assert_eq!(e.get_attempts(), 1);
+            assert_eq!(e.get_source().get_status(), Some(429));
+        }
+        _ => panic!("Expected a request error"),
+    }
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    backend.push_response(
+        "/files",
+        FakeResponse::TooManyRequests {
+            retry_after: Some(1),
+        },
+    );
+    let client = backend
+        .client_builder()
+        .retry_policy(RetryPolicy::default().with_max_retry_after(Duration::from_secs(1)))
+        .build();
+    let tile = client.download_tile(Tile::new(0, 0)).await.unwrap();
+    assert_eq!(tile.get_attempts(), 2);
+}
+
+#[tokio::test]
+async fn revalidates_disk_cache_with_etag() {
+    let backend = FakeBackend::start().await.unwrap();
+    backend.fill_tile(Tile::new(1, 1), Color::Blue);
+    let directory =
+        std::env::temp_dir().join(format!("wplace-fake-backend-{}", std::process::id()));
+    let disk_cache = DiskTileCache::new(&directory).with_max_age(Duration::ZERO);
+
+    let client = backend
+        .client_builder()
+        .disk_cache(disk_cache.clone())
+        .build();
+    client.download_tile(Tile::new(1, 1)).await.unwrap();
+
+    // A fresh client only has the disk cache to go by
+    let client = backend
+        .client_builder()
+        .disk_cache(disk_cache.clone())
+        .build();
+    let tile = client.download_tile(Tile::new(1, 1)).await.unwrap();
+    assert_eq!(
+        tile.get_value().get_pixel(0, 0).0,
+        <[u8; 4]>::from(Color::Blue)
+    );
+    assert_eq!(backend.get_request_count("/files"), 2);
+
+    disk_cache.clear().await.unwrap();
+    let _ = std::fs::remove_dir_all(directory);
+}
+
+#[tokio::test]
+async fn only_caches_valid_tiles_on_disk() {
+    let backend = FakeBackend::start().await.unwrap();
+    backend.insert_tile_png(Tile::new(2, 3), b"<html>Cloudflare</html>".to_vec());
+    backend.insert_tile(
+        Tile::new(3, 3),
+        &RgbaImage::from_pixel(10, 10, image::Rgba(Color::Red.into())),
+    );
+    let directory =
+        std::env::temp_dir().join(format!("wplace-broken-tiles-{}", std::process::id()));
+    let disk_cache = DiskTileCache::new(&directory);
+    let client = backend
+        .client_builder()
+        .disk_cache(disk_cache.clone())
+        .build();
+
+    assert!(matches!(
+        client.download_tile(Tile::new(2, 3)).await,
+        Err(TileDownloadError::InvalidTile { .. })
+    ));
+    assert!(!directory.join("2").join("3.png").exists());
+
+    backend.fill_tile(Tile::new(2, 3), Color::Green);
+    let tile = client.download_tile(Tile::new(2, 3)).await.unwrap();
+    assert_eq!(
+        tile.get_value().get_pixel(0, 0).0,
+        <[u8; 4]>::from(Color::Green)
+    );
+    assert_eq!(backend.get_request_count("/files/s0/tiles/2/3.png"), 2);
+
+    // Repaired tiles are cached as repaired
+    let client = backend
+        .client_builder()
+        .disk_cache(disk_cache.clone())
+        .tile_validation(TileValidation::Repair)
+        .build();
+    client.download_tile(Tile::new(3, 3)).await.unwrap();
+    let cached = image::open(directory.join("3").join("3.png")).unwrap();
+    assert_eq!(cached.width(), 1000);
+
+    disk_cache.clear().await.unwrap();
+}
+
+#[tokio::test]
+async fn evicts_the_oldest_tiles_past_the_disk_cache_size() {
+    let backend = FakeBackend::start().await.unwrap();
+    for x in 0..3 {
+        backend.fill_tile(Tile::new(x, 0), Color::Red);
+    }
+    let directory = std::env::temp_dir().join(format!("wplace-cache-size-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+
+    let client = backend
+        .client_builder()
+        .disk_cache(DiskTileCache::new(&directory))
+        .build();
+    client.download_tile(Tile::new(0, 0)).await.unwrap();
+    let tile_size = std::fs::metadata(directory.join("0").join("0.png"))
+        .unwrap()
+        .len();
+
+    // Entries are ordered by the second they were fetched at
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    // A new cache finds the first tile in the directory
+    let disk_cache = DiskTileCache::new(&directory).with_max_size(tile_size * 2);
+    let client = backend
+        .client_builder()
+        .disk_cache(disk_cache.clone())
+        .build();
+    client.download_tile(Tile::new(1, 0)).await.unwrap();
+    assert!(directory.join("0").join("0.png").exists());
+    client.download_tile(Tile::new(2, 0)).await.unwrap();
+    assert!(!directory.join("0").join("0.png").exists());
+    assert!(!directory.join("0").join("0.json").exists());
+    assert!(directory.join("1").join("0.png").exists());
+    assert!(directory.join("2").join("0.png").exists());
+
+    disk_cache.clear().await.unwrap();
+}
+
+#[tokio::test]
+async fn caches_concurrent_downloads_of_the_same_tile() {
+    let backend = FakeBackend::start().await.unwrap();
+    backend.fill_tile(Tile::new(6, 6), Color::Blue);
+    let directory = std::env::temp_dir().join(format!("wplace-concurrent-{}", std::process::id()));
+    let disk_cache = DiskTileCache::new(&directory).with_max_age(Duration::ZERO);
+
+    for _ in 0..5 {
+        let client = backend
+            .client_builder()
+            .disk_cache(disk_cache.clone())
+            .build();
+        let downloads = (0..8).map(|_| client.download_tile(Tile::new(6, 6)));
+        for download in futures::future::join_all(downloads).await {
+            download.unwrap();
+        }
+    }
+
+    let leftovers = std::fs::read_dir(directory.join("6"))
+        .unwrap()
+        .map(|v| v.unwrap().file_name().into_string().unwrap())
+        .filter(|v| v.ends_with(".tmp"))
+        .collect::<Vec<_>>();
+    assert!(leftovers.is_empty(), "{leftovers:?}");
+    let cached = image::open(directory.join("6").join("6.png")).unwrap();
+    assert_eq!(
+        cached.to_rgba8().get_pixel(0, 0).0,
+        <[u8; 4]>::from(Color::Blue)
+    );
+
+    disk_cache.clear().await.unwrap();
+}
+
+#[tokio::test]
+async fn geocodes_through_nominatim_and_caches_the_area() {
+    let backend = FakeBackend::start().await.unwrap();
+    backend.set_nominatim_display_name("Roma, Lazio, Italia");
+    let client = backend.client_builder().build();
+    let coords = MapCoords::from_tile_coords(&TileCoords::new(1094, 759, 500, 500), 1, 1);
+
+    let data = NominatimData::load_data(&client, &coords).await.unwrap();
+    assert_eq!(data.get_value().get_display_name(), "Roma, Lazio, Italia");
+    assert_eq!(data.get_attempts(), 1);
+
+    let data = NominatimData::load_data(&client, &coords).await.unwrap();
+    assert_eq!(data.get_attempts(), 0);
+}
+
+#[tokio::test]
+async fn builds_templates_and_compares_them_with_the_canvas() {
+    let backend = FakeBackend::start().await.unwrap();
+    backend.set_default_tile(Some(Color::White));
+    backend.set_nominatim_response(Some(String::from(r#"{"error":"Unable to geocode"}"#)));
+    let client = backend.client_builder().build();
+
+    let template = ImageData::new(RgbaImage::from_pixel(
+        20,
+        10,
+        image::Rgba(Color::Black.into()),
+    ))
+    .unwrap();
+    let template = TemplateData::new(
+        &client,
+        "Test",
+        TileCoords::new(3, 4, 990, 995),
+        "test.png",
+        template,
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(matches!(
+        template.get_location_data(),
+        LocationData::Nominatim(v) if v.get_display_name() == "Unknown"
+    ));
+    let center = MapCoords::from_global_pixel(GlobalPixel::new(4000, 5000).unwrap(), 20, 10);
+    assert_eq!(template.get_center_coordiantes(), &center);
+
+    let comparison = ImageComparison::compare_with_source(
+        template.get_image(),
+        template.get_top_left_corner(),
+        &client,
+    )
+    .await
+    .unwrap();
+    assert_eq!(comparison.get_total_different_px(), 200);
+    assert_eq!(backend.get_request_count("/files"), 4);
+}
+
+#[tokio::test]
+async fn downloads_tiles_concurrently_up_to_the_limit() {
+    // Long enough for curl's polling, which adds a few hundred milliseconds to every request,
+    // not to blur the difference
+    const LATENCY: Duration = Duration::from_secs(1);
+    let backend = FakeBackend::start().await.unwrap();
+    backend.set_default_tile(Some(Color::Red));
+    backend.set_latency(LATENCY);
+    // Covers 4 tiles, from 3, 3 to 4, 4
+    let rect = CanvasRect::from_tile_coords(&TileCoords::new(3, 3, 900, 900), 200, 200).unwrap();
+
+    let client = backend.client_builder().build();
+    let start = Instant::now();
+    ImageData::from_site_rect(&client, &rect).await.unwrap();
+    let elapsed = start.elapsed();
+    assert!(elapsed >= LATENCY, "{elapsed:?}");
+    assert!(elapsed < LATENCY * 2, "{elapsed:?}");
+
+    let client = backend.client_builder().concurrency(1).build();
+    let start = Instant::now();
+    ImageData::from_site_rect(&client, &rect).await.unwrap();
+    let elapsed = start.elapsed();
+    assert!(elapsed >= LATENCY * 4, "{elapsed:?}");
+    assert_eq!(backend.get_request_count("/files"), 8);
+}
+
+#[tokio::test]
+async fn downloads_shared_tiles_once_for_many_templates() {
+    let backend = FakeBackend::start().await.unwrap();
+    for (tile, color) in [
+        (Tile::new(0, 0), Color::Red),
+        (Tile::new(1, 0), Color::Green),
+        (Tile::new(0, 1), Color::Blue),
+        (Tile::new(1, 1), Color::Black),
+        (Tile::new(2, 1), Color::White),
+    ] {
+        backend.fill_tile(tile, color);
+    }
+    let client = backend.client_builder().build();
+
+    let new_template = |top_left_corner, width, height| {
+        let image = ImageData::new(RgbaImage::from_pixel(
+            width,
+            height,
+            image::Rgba(Color::Red.into()),
+        ))
+        .unwrap();
+        TemplateData::new(
+            &client,
+            "Test",
+            top_left_corner,
+            "test.png",
+            image,
+            Some(String::from("Somewhere")),
+        )
+    };
+    // Covers 0, 0 to 1, 1, then 1, 1 to 2, 1
+    let templates = [
+        new_template(TileCoords::new(0, 0, 900, 900), 300, 300)
+            .await
+            .unwrap(),
+        new_template(TileCoords::new(1, 1, 100, 100), 1000, 50)
+            .await
+            .unwrap(),
+    ];
+
+    let areas = TemplateData::download_template_areas_on_map(&templates, &client)
+        .await
+        .unwrap();
+    assert_eq!(areas.len(), 2);
+    assert_eq!(backend.get_request_count("/files"), 5);
+
+    let client = backend.client_builder().build();
+    for (template, area) in templates.iter().zip(&areas) {
+        let single = ImageData::from_site_rect(&client, &template.get_canvas_rect())
+            .await
+            .unwrap();
+        assert_eq!(area.get_image().to_rgba8(), single.get_image().to_rgba8());
+        assert_eq!(area.get_color_counts(), single.get_color_counts());
+    }
+    assert_eq!(areas[0].get_color_counts()[&Color::Black], 200 * 200);
+    assert_eq!(areas[1].get_color_counts()[&Color::White], 100 * 50);
+}
+
+#[tokio::test]
+async fn reads_blank_tiles_as_transparent() {
+    let backend = FakeBackend::start().await.unwrap();
+    backend.fill_tile(Tile::new(0, 0), Color::Red);
+    let client = backend.client_builder().build();
+
+    let top_left_corner = TileCoords::new(0, 0, 990, 0);
+    let current = ImageData::from_site_coords(&client, &top_left_corner, 20, 1)
+        .await
+        .unwrap();
+    assert_eq!(current.get_color_counts()[&Color::Red], 10);
+    assert_eq!(current.get_total_px(), 10);
+
+    let template =
+        ImageData::new(RgbaImage::from_pixel(20, 1, image::Rgba(Color::Red.into()))).unwrap();
+    let comparison = ImageComparison::compare_with_source(&template, &top_left_corner, &client)
+        .await
+        .unwrap();
+    assert_eq!(comparison.get_total_different_px(), 10);
+    assert_eq!(backend.get_request_count("/files/s0/tiles/1/0.png"), 1);
+
+    let directory = std::env::temp_dir().join(format!("wplace-blank-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(directory.join("0")).unwrap();
+    RgbaImage::from_pixel(1000, 1000, image::Rgba(Color::Red.into()))
+        .save(directory.join("0").join("0.png"))
+        .unwrap();
+
+    assert!(matches!(
+        ImageData::from_site_coords(
+            &DirectoryTileSource::new(&directory),
+            &top_left_corner,
+            20,
+            1
+        )
+        .await,
+        Err(ImageDataError::TileSourceError(TileSourceError::MissingTile(tile))) if tile == Tile::new(1, 0)
+    ));
+    let source = DirectoryTileSource::new(&directory).with_blank_missing_tiles(true);
+    let current = ImageData::from_site_coords(&source, &top_left_corner, 20, 1)
+        .await
+        .unwrap();
+    assert_eq!(current.get_total_px(), 10);
+
+    let _ = std::fs::remove_dir_all(directory);
+}
+
+#[tokio::test]
+async fn looks_painters_up() {
+    let backend = FakeBackend::start().await.unwrap();
+    let coords = TileCoords::new(5, 6, 7, 8);
+    backend.set_pixel_info(coords, 42, "painter", Some((3, "alliance")));
+    let client = backend.client_builder().build();
+
+    let infos = client
+        .get_pixels_info([coords, TileCoords::new(5, 6, 7, 9), coords])
+        .await
+        .unwrap();
+    let painter = infos[0].get_painter().unwrap();
+    assert_eq!(painter.get_id(), 42);
+    assert_eq!(painter.get_name(), "painter");
+    assert_eq!(painter.get_alliance().unwrap().get_name(), "alliance");
+    assert!(infos[1].get_painter().is_none());
+    assert_eq!(infos[2], infos[0]);
+    assert_eq!(backend.get_request_count("/s0/pixel"), 2);
+}
+
+#[tokio::test]
+async fn dumps_regions_and_resumes_after_failures() {
+    let backend = FakeBackend::start().await.unwrap();
+    backend.fill_tile(Tile::new(4, 7), Color::Red);
+    backend.fill_tile(Tile::new(5, 7), Color::Green);
+    backend.fill_tile(Tile::new(5, 8), Color::Blue);
+    backend.push_response(
+        "/files/s0/tiles/5/8.png",
+        FakeResponse::Status {
+            status: 500,
+            body: String::from("Broken"),
+        },
+    );
+    let client = backend
+        .client_builder()
+        .retry_policy(RetryPolicy::no_retries())
+        .build();
+    let directory = std::env::temp_dir().join(format!("wplace-dump-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    let dumper = TileDumper::new(&directory, Tile::new(5, 8), Tile::new(4, 7));
+
+    let report = dumper.dump(&client).await.unwrap();
+    assert_eq!(report.get_downloaded(), 2);
+    assert_eq!(report.get_missing(), [Tile::new(4, 8)]);
+    assert_eq!(report.get_failed().len(), 1);
+    assert!(!report.is_complete());
+
+    // As if a run got killed in the middle of an entry
+    let mut manifest = std::fs::OpenOptions::new()
+        .append(true)
+        .open(dumper.get_manifest_path())
+        .unwrap();
+    std::io::Write::write_all(&mut manifest, br#"{"x":5,"y"#).unwrap();
+
+    let report = dumper.dump(&client).await.unwrap();
+    assert_eq!(report.get_downloaded(), 1);
+    assert_eq!(report.get_skipped(), 3);
+    assert!(report.is_complete());
+    assert_eq!(backend.get_request_count("/files/s0/tiles/5/7.png"), 1);
+
+    let report = dumper.dump(&client).await.unwrap();
+    assert_eq!(report.get_skipped(), 4);
+    assert_eq!(backend.get_request_count("/files/s0/tiles/5/8.png"), 2);
+
+    // Blank tiles are written too, so the whole dump can be read back
+    let image = ImageData::from_site_coords(
+        &DirectoryTileSource::new(&directory),
+        &TileCoords::new(4, 7, 0, 0),
+        2000,
+        2000,
+    )
+    .await
+    .unwrap();
+    assert_eq!(image.get_color_counts()[&Color::Red], 1_000_000);
+    assert_eq!(image.get_color_counts()[&Color::Blue], 1_000_000);
+    assert_eq!(image.get_total_px(), 3_000_000);
+
+    let tile = image::open(dumper.get_tile_path(Tile::new(5, 8))).unwrap();
+    assert_eq!(
+        tile.to_rgba8().get_pixel(0, 0).0,
+        <[u8; 4]>::from(Color::Blue)
+    );
+
+    let _ = std::fs::remove_dir_all(directory);
+}
+
+#[tokio::test]
+async fn rejects_or_repairs_invalid_tiles() {
+    let backend = FakeBackend::start().await.unwrap();
+    backend.insert_tile_png(Tile::new(0, 0), b"<html>Cloudflare</html>".to_vec());
+    let mut undersized = RgbaImage::from_pixel(10, 10, image::Rgba([0xEC, 0x1D, 0x25, 200]));
+    undersized.put_pixel(0, 0, image::Rgba([0, 0, 0, 10]));
+    backend.insert_tile(Tile::new(1, 0), &undersized);
+    let client = backend.client_builder().build();
+
+    assert!(matches!(
+        client.download_tile(Tile::new(0, 0)).await,
+        Err(TileDownloadError::InvalidTile {
+            source: TileValidationError::InvalidSignature(_),
+            ..
+        })
+    ));
+    assert!(matches!(
+        client.download_tile(Tile::new(1, 0)).await,
+        Err(TileDownloadError::InvalidTile {
+            source: TileValidationError::InvalidSize {
+                width: 10,
+                height: 10
+            },
+            ..
+        })
+    ));
+
+    let client = backend
+        .client_builder()
+        .tile_validation(TileValidation::Repair)
+        .build();
+    let tile = client.download_tile(Tile::new(1, 0)).await.unwrap();
+    let tile = tile.get_value();
+    assert_eq!(tile.dimensions(), (1000, 1000));
+    assert_eq!(tile.get_pixel(0, 0).0, [0, 0, 0, 0]);
+    assert_eq!(tile.get_pixel(5, 5).0, <[u8; 4]>::from(Color::Red));
+    assert_eq!(tile.get_pixel(500, 500).0, [0, 0, 0, 0]);
+}
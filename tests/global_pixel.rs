@@ -0,0 +1,126 @@
+use wplace_core_library::{
+    global_pixel::{GlobalPixel, GlobalPixelError},
+    tile_coords::TileCoords,
+};
+
+const LAST: u32 = GlobalPixel::CANVAS_SIZE - 1;
+
+fn pixel(x: u32, y: u32) -> GlobalPixel {
+    GlobalPixel::new(x, y).unwrap()
+}
+
+#[test]
+fn rejects_pixels_outside_of_the_canvas() {
+    assert_eq!(pixel(LAST, LAST).get_x(), LAST);
+    for (x, y) in [
+        (GlobalPixel::CANVAS_SIZE, 0),
+        (0, GlobalPixel::CANVAS_SIZE),
+        (u32::MAX, u32::MAX),
+    ] {
+        assert_eq!(
+            GlobalPixel::new(x, y),
+            Err(GlobalPixelError::OutOfCanvas {
+                x: x as u64,
+                y: y as u64
+            })
+        );
+    }
+}
+
+#[test]
+fn converts_from_tile_coords_up_to_the_canvas_edges() {
+    let last = TileCoords::new(2047, 2047, 999, 999);
+    assert_eq!(GlobalPixel::try_from(last), Ok(pixel(LAST, LAST)));
+    assert_eq!(TileCoords::from(pixel(LAST, LAST)), last);
+    assert_eq!(
+        GlobalPixel::try_from(TileCoords::new(2047, 2047, 999, 1000)),
+        Err(GlobalPixelError::OutOfTile { x: 999, y: 1000 })
+    );
+    assert_eq!(
+        GlobalPixel::try_from(&TileCoords::new(0, 0, u16::MAX, 0)),
+        Err(GlobalPixelError::OutOfTile { x: u16::MAX, y: 0 })
+    );
+    assert_eq!(
+        GlobalPixel::try_from(TileCoords::new(2048, 0, 0, 0)),
+        Err(GlobalPixelError::OutOfCanvas {
+            x: GlobalPixel::CANVAS_SIZE as u64,
+            y: 0
+        })
+    );
+    assert!(matches!(
+        GlobalPixel::try_from(TileCoords::new(u16::MAX, u16::MAX, 999, 999)),
+        Err(GlobalPixelError::OutOfCanvas { .. })
+    ));
+}
+
+#[test]
+fn checked_arithmetic_stops_at_the_edges() {
+    assert_eq!(pixel(LAST - 1, 0).checked_add(1, 0), Some(pixel(LAST, 0)));
+    assert_eq!(pixel(LAST, 0).checked_add(1, 0), None);
+    assert_eq!(pixel(0, LAST).checked_add(0, 1), None);
+    assert_eq!(pixel(LAST, LAST).checked_add(u32::MAX, 0), None);
+    assert_eq!(pixel(1, 1).checked_sub(1, 1), Some(pixel(0, 0)));
+    assert_eq!(pixel(0, 5).checked_sub(1, 0), None);
+    assert_eq!(pixel(5, 0).checked_sub(0, u32::MAX), None);
+}
+
+#[test]
+fn saturating_arithmetic_clamps_to_the_edges() {
+    assert_eq!(pixel(LAST, LAST).saturating_add(1, 1), pixel(LAST, LAST));
+    assert_eq!(
+        pixel(LAST, 7).saturating_add(u32::MAX, u32::MAX),
+        pixel(LAST, LAST)
+    );
+    assert_eq!(pixel(10, 20).saturating_add(5, 0), pixel(15, 20));
+    assert_eq!(pixel(0, 0).saturating_sub(1, 1), pixel(0, 0));
+    assert_eq!(pixel(10, 20).saturating_sub(u32::MAX, 5), pixel(0, 15));
+}
+
+#[test]
+fn wrapping_arithmetic_goes_around_the_canvas() {
+    assert_eq!(pixel(LAST, LAST).wrapping_add(1, 1), pixel(0, 0));
+    assert_eq!(pixel(0, 0).wrapping_sub(1, 1), pixel(LAST, LAST));
+    assert_eq!(
+        pixel(0, 0).wrapping_add(GlobalPixel::CANVAS_SIZE, 3 * GlobalPixel::CANVAS_SIZE + 2),
+        pixel(0, 2)
+    );
+    // u32::MAX is 2097 canvases and 311295 pixels
+    assert_eq!(pixel(LAST, 0).wrapping_add(u32::MAX, 0), pixel(311_294, 0));
+    assert_eq!(
+        pixel(0, 0).wrapping_sub(u32::MAX, 0),
+        pixel(LAST - 311_294, 0)
+    );
+
+    assert_eq!(
+        pixel(LAST, 10).horizontal_wrapping_add(1, 1),
+        Some(pixel(0, 11))
+    );
+    assert_eq!(pixel(LAST, LAST).horizontal_wrapping_add(1, 1), None);
+    assert_eq!(pixel(5, 0).horizontal_wrapping_add(0, u32::MAX), None);
+    assert_eq!(
+        pixel(0, 10).horizontal_wrapping_sub(1, 10),
+        Some(pixel(LAST, 0))
+    );
+    assert_eq!(pixel(0, 0).horizontal_wrapping_sub(1, 1), None);
+}
+
+#[test]
+fn areas_end_around_the_antimeridian() {
+    assert_eq!(pixel(LAST, 0).get_area_end(2, 1), Ok(pixel(0, 0)));
+    assert_eq!(
+        pixel(0, 0).get_area_end(GlobalPixel::CANVAS_SIZE, 1),
+        Ok(pixel(LAST, 0))
+    );
+    assert_eq!(pixel(3, 4).get_area_end(0, 0), Ok(pixel(3, 4)));
+    for (width, height) in [(GlobalPixel::CANVAS_SIZE + 1, 1), (1, 2), (u32::MAX, 1)] {
+        assert_eq!(
+            pixel(0, LAST).get_area_end(width, height),
+            Err(GlobalPixelError::AreaOutOfCanvas {
+                x: 0,
+                y: LAST,
+                width,
+                height
+            })
+        );
+    }
+}
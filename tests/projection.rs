@@ -0,0 +1,311 @@
+use wplace_core_library::{
+    canvas_rect::CanvasRect,
+    global_pixel::{GlobalPixel, GlobalPixelError},
+    map_coords::MapCoords,
+    tile_coords::TileCoords,
+    web_mercator::WebMercatorCoords,
+};
+
+#[test]
+fn tile_coords_round_trip_through_map_coords() {
+    let mut rng = fastrand::Rng::with_seed(19);
+    let edges = [
+        TileCoords::new(0, 0, 0, 0),
+        TileCoords::new(2047, 2047, 999, 999),
+        TileCoords::new(1023, 1023, 999, 999),
+        TileCoords::new(1024, 1024, 0, 0),
+    ];
+    let random = (0..10_000).map(|_| {
+        TileCoords::new(
+            rng.u16(0..2048),
+            rng.u16(0..2048),
+            rng.u16(0..1000),
+            rng.u16(0..1000),
+        )
+    });
+
+    for coords in edges.into_iter().chain(random) {
+        let map_coords = MapCoords::from_tile_coords(&coords, 1, 1);
+        assert_eq!(TileCoords::from_map_coords(&map_coords), Ok(coords));
+    }
+}
+
+#[test]
+fn global_pixel_round_trips_through_map_coords() {
+    let mut rng = fastrand::Rng::with_seed(19);
+    for _ in 0..10_000 {
+        let pixel = GlobalPixel::new(
+            rng.u32(0..GlobalPixel::CANVAS_SIZE),
+            rng.u32(0..GlobalPixel::CANVAS_SIZE),
+        )
+        .unwrap();
+        let map_coords = MapCoords::from_global_pixel(pixel, 1, 1);
+        assert_eq!(GlobalPixel::from_map_coords(&map_coords), Ok(pixel));
+    }
+}
+
+#[test]
+fn points_inside_a_pixel_map_to_it() {
+    let pixel = GlobalPixel::new(1_234_567, 765_432).unwrap();
+    let corner = MapCoords::from_global_pixel(pixel, 1, 1);
+    let opposite = MapCoords::from_global_pixel(pixel.checked_add(1, 1).unwrap(), 1, 1);
+
+    let middle = MapCoords::new(
+        (corner.get_lat() + opposite.get_lat()) / 2.0,
+        (corner.get_lng() + opposite.get_lng()) / 2.0,
+        0.0,
+    );
+    assert_eq!(GlobalPixel::from_map_coords(&middle), Ok(pixel));
+    assert_eq!(
+        GlobalPixel::from_map_coords(&opposite),
+        Ok(pixel.checked_add(1, 1).unwrap())
+    );
+}
+
+#[test]
+fn points_off_the_canvas_are_rejected() {
+    for (lat, lng) in [
+        (90.0, 0.0),
+        (-86.0, 0.0),
+        (f64::NAN, 0.0),
+        (0.0, f64::NAN),
+        (0.0, f64::INFINITY),
+    ] {
+        assert!(matches!(
+            GlobalPixel::from_map_coords(&MapCoords::new(lat, lng, 0.0)),
+            Err(GlobalPixelError::MapCoordsOutOfCanvas { .. })
+        ));
+    }
+}
+
+#[test]
+fn longitudes_go_around_the_antimeridian() {
+    let pixel = |lat, lng| GlobalPixel::from_map_coords(&MapCoords::new(lat, lng, 0.0));
+    let equator = GlobalPixel::CANVAS_SIZE / 2;
+
+    assert_eq!(pixel(0.0, 180.0), Ok(GlobalPixel::new(0, equator).unwrap()));
+    assert_eq!(pixel(0.0, 180.0), pixel(0.0, -180.0));
+    assert_eq!(pixel(0.0, 540.0), pixel(0.0, -180.0));
+    assert_eq!(pixel(45.0, 190.0), pixel(45.0, -170.0));
+    assert_eq!(pixel(45.0, -190.0), pixel(45.0, 170.0));
+    assert_eq!(
+        "https://wplace.live/?lat=45&lng=190&zoom=10".parse::<TileCoords>(),
+        Ok(TileCoords::from_map_coords(&MapCoords::new(45.0, -170.0, 0.0)).unwrap())
+    );
+
+    let mercator = |x| GlobalPixel::from_web_mercator(&WebMercatorCoords::new(x, 0.0));
+    let edge = WebMercatorCoords::CANVAS_EDGE;
+    assert_eq!(mercator(edge * 1.01), mercator(-edge * 0.99));
+
+    // Rects past 180 end up at the same place as their wrapped counterpart
+    let rect = geo_types::Rect::new(
+        geo_types::coord! { x: 181.0, y: 10.0 },
+        geo_types::coord! { x: 182.0, y: 11.0 },
+    );
+    let wrapped = geo_types::Rect::new(
+        geo_types::coord! { x: -179.0, y: 10.0 },
+        geo_types::coord! { x: -178.0, y: 11.0 },
+    );
+    assert_eq!(CanvasRect::try_from(rect), CanvasRect::try_from(wrapped));
+}
+
+#[test]
+fn zooms_to_fit_rects_in_viewports() {
+    let rect = CanvasRect::new(GlobalPixel::new(1_000_000, 800_000).unwrap(), 3000, 1000).unwrap();
+
+    // 4000x2000 with the margin, limited by the width: a quarter of a screen pixel per pixel
+    assert_eq!(MapCoords::get_zoom_to_fit(&rect, 500, 1000, 1000), 9.96);
+    // A screen pixel per pixel
+    assert_eq!(MapCoords::get_zoom_to_fit(&rect, 0, 3000, 2000), 11.96);
+    assert_eq!(
+        MapCoords::get_zoom_to_fit(&rect, 0, 0, 1000),
+        MapCoords::MIN_ZOOM
+    );
+    let pixel = CanvasRect::new(GlobalPixel::new(0, 0).unwrap(), 1, 1).unwrap();
+    assert_eq!(
+        MapCoords::get_zoom_to_fit(&pixel, 0, 1920, 1080),
+        MapCoords::MAX_ZOOM
+    );
+
+    let map_coords = MapCoords::from_canvas_rect(&rect, 500, 1000, 1000);
+    let center = MapCoords::from_global_pixel(GlobalPixel::new(1_001_500, 800_500).unwrap(), 1, 1);
+    assert_eq!(map_coords.get_lat(), center.get_lat());
+    assert_eq!(map_coords.get_lng(), center.get_lng());
+    assert_eq!(map_coords.get_zoom(), 9.96);
+}
+
+#[test]
+fn centers_rects_across_the_antimeridian() {
+    let rect = CanvasRect::new(
+        GlobalPixel::new(GlobalPixel::CANVAS_SIZE - 100, 1_024_000).unwrap(),
+        300,
+        1,
+    )
+    .unwrap();
+    let map_coords = MapCoords::from_canvas_rect(&rect, 0, 1000, 1000);
+    assert!(
+        (map_coords.get_lng() - 50.0 * 360.0 / GlobalPixel::CANVAS_SIZE as f64 + 180.0).abs()
+            < 1e-9
+    );
+}
+
+#[test]
+fn keeps_footprints_across_the_antimeridian_in_one_piece() {
+    let rect = CanvasRect::new(
+        GlobalPixel::new(GlobalPixel::CANVAS_SIZE - 100, 1_024_000).unwrap(),
+        300,
+        200,
+    )
+    .unwrap();
+    let px = 360.0 / GlobalPixel::CANVAS_SIZE as f64;
+
+    let [top_left, top_right, bottom_right, bottom_left] = rect.get_corners();
+    assert!((top_left.get_lng() - (180.0 - 100.0 * px)).abs() < 1e-9);
+    assert!((top_right.get_lng() - (180.0 + 200.0 * px)).abs() < 1e-9);
+    assert_eq!(top_right.get_lng(), bottom_right.get_lng());
+    assert_eq!(top_left.get_lng(), bottom_left.get_lng());
+
+    let footprint = rect.get_footprint();
+    assert_eq!(footprint.len(), 5);
+    assert_eq!(footprint[0], footprint[4]);
+    assert_eq!(footprint[2], bottom_right);
+    let (min, max) = footprint
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(min, max), v| {
+            (min.min(v.get_lng()), max.max(v.get_lng()))
+        });
+    assert!((max - min - 300.0 * px).abs() < 1e-9);
+
+    let geo_rect = geo_types::Rect::from(rect);
+    assert_eq!(geo_rect.max().x, top_right.get_lng());
+    assert_eq!(geo_rect.min().x, top_left.get_lng());
+}
+
+#[test]
+fn measures_rects_on_the_ground() {
+    let equator = GlobalPixel::CANVAS_SIZE / 2;
+    let rect = CanvasRect::new(GlobalPixel::new(equator, equator).unwrap(), 1, 1).unwrap();
+    let equator_px =
+        2.0 * std::f64::consts::PI * MapCoords::EARTH_RADIUS / GlobalPixel::CANVAS_SIZE as f64;
+    assert!((rect.get_meters_per_pixel() - equator_px).abs() < 1e-6);
+    assert!((rect.get_area_km2() - (equator_px / 1000.0).powi(2)).abs() < 1e-9);
+
+    // Pixels at 60° are half as big on the ground
+    let sixty = GlobalPixel::from_map_coords(&MapCoords::new(60.0, 0.0, 0.0)).unwrap();
+    let rect = CanvasRect::new(sixty, 1, 1).unwrap();
+    assert!((rect.get_meters_per_pixel() / equator_px - 0.5).abs() < 1e-4);
+
+    // The whole canvas, up to ±85.05°
+    let canvas = CanvasRect::new(
+        GlobalPixel::new(0, 0).unwrap(),
+        GlobalPixel::CANVAS_SIZE,
+        GlobalPixel::CANVAS_SIZE,
+    )
+    .unwrap();
+    let max_lat = std::f64::consts::PI.sinh().atan();
+    let expected =
+        4.0 * std::f64::consts::PI * MapCoords::EARTH_RADIUS.powi(2) * max_lat.sin() / 1_000_000.0;
+    assert!((canvas.get_area_km2() / expected - 1.0).abs() < 1e-12);
+    let [top_left, top_right, bottom_right, bottom_left] = canvas.get_corners();
+    assert_eq!((top_left.get_lng(), top_right.get_lng()), (-180.0, 180.0));
+    assert!((top_left.get_lat() - max_lat.to_degrees()).abs() < 1e-9);
+    assert_eq!(bottom_right.get_lat(), bottom_left.get_lat());
+
+    let footprint = canvas.get_footprint();
+    assert_eq!(footprint.len(), 5);
+    assert_eq!(footprint[0], footprint[4]);
+    assert_eq!(footprint[1], bottom_left);
+}
+
+#[test]
+fn converts_to_and_from_web_mercator() {
+    let edge = WebMercatorCoords::CANVAS_EDGE;
+    assert!((edge - 20_037_508.342789244).abs() < 1e-6);
+
+    let corner = WebMercatorCoords::from_global_pixel(GlobalPixel::new(0, 0).unwrap());
+    assert_eq!((corner.get_x(), corner.get_y()), (-edge, edge));
+    let center = GlobalPixel::new(1_024_000, 1_024_000).unwrap();
+    let mercator = WebMercatorCoords::from(center);
+    assert_eq!((mercator.get_x(), mercator.get_y()), (0.0, 0.0));
+    assert_eq!(GlobalPixel::from_web_mercator(&mercator), Ok(center));
+    assert!(matches!(
+        GlobalPixel::from_web_mercator(&WebMercatorCoords::new(0.0, edge * 1.01)),
+        Err(GlobalPixelError::WebMercatorOutOfCanvas { .. })
+    ));
+
+    let map_coords = MapCoords::new(45.0, 9.0, 0.0);
+    let mercator = WebMercatorCoords::from(&map_coords);
+    assert!((mercator.get_x() - 1_001_875.417).abs() < 1e-3);
+    assert!((mercator.get_y() - 5_621_521.486).abs() < 1e-3);
+    let back = mercator.to_map_coords(0.0);
+    assert!((back.get_lat() - 45.0).abs() < 1e-12 && (back.get_lng() - 9.0).abs() < 1e-12);
+
+    let mut rng = fastrand::Rng::with_seed(25);
+    for _ in 0..10_000 {
+        let pixel = GlobalPixel::new(
+            rng.u32(0..GlobalPixel::CANVAS_SIZE),
+            rng.u32(0..GlobalPixel::CANVAS_SIZE),
+        )
+        .unwrap();
+        let mercator = WebMercatorCoords::from_global_pixel(pixel);
+        assert_eq!(GlobalPixel::from_web_mercator(&mercator), Ok(pixel));
+    }
+}
+
+#[test]
+fn converts_to_and_from_geo_types() {
+    let map_coords = MapCoords::new(45.0, 9.0, 13.0);
+    let point = geo_types::Point::from(&map_coords);
+    assert_eq!((point.x(), point.y()), (9.0, 45.0));
+    let back = MapCoords::from(point);
+    assert_eq!((back.get_lat(), back.get_lng()), (45.0, 9.0));
+    let point = geo_types::Point::from(WebMercatorCoords::new(1.0, 2.0));
+    assert_eq!(
+        WebMercatorCoords::from(point),
+        WebMercatorCoords::new(1.0, 2.0)
+    );
+
+    let rect = CanvasRect::new(GlobalPixel::new(1_000_990, 700_000).unwrap(), 30, 20).unwrap();
+    let geo_rect = geo_types::Rect::from(rect);
+    let [top_left, _, bottom_right, _] = rect.get_corners();
+    assert_eq!(geo_rect.min().x, top_left.get_lng());
+    assert_eq!(geo_rect.max().y, top_left.get_lat());
+    assert_eq!(geo_rect.max().x, bottom_right.get_lng());
+    assert_eq!(geo_rect.min().y, bottom_right.get_lat());
+    assert_eq!(CanvasRect::try_from(geo_rect), Ok(rect));
+
+    let polygon = geo_types::Polygon::from(rect);
+    assert_eq!(polygon.exterior().0.len(), 5);
+    assert!(
+        polygon
+            .exterior()
+            .0
+            .iter()
+            .all(|v| geo_rect.min().x <= v.x && v.x <= geo_rect.max().x)
+    );
+
+    let wrapping = CanvasRect::new(
+        GlobalPixel::new(GlobalPixel::CANVAS_SIZE - 10, 700_000).unwrap(),
+        30,
+        20,
+    )
+    .unwrap();
+    let geo_rect = geo_types::Rect::from(wrapping);
+    assert!(geo_rect.max().x > 180.0 && geo_rect.min().x < 180.0);
+    assert_eq!(CanvasRect::try_from(geo_rect), Ok(wrapping));
+
+    let mut rng = fastrand::Rng::with_seed(25);
+    for _ in 0..10_000 {
+        let rect = CanvasRect::new(
+            GlobalPixel::new(
+                rng.u32(0..GlobalPixel::CANVAS_SIZE),
+                rng.u32(0..GlobalPixel::CANVAS_SIZE - 5000),
+            )
+            .unwrap(),
+            rng.u32(1..5000),
+            rng.u32(1..5000),
+        )
+        .unwrap();
+        assert_eq!(CanvasRect::try_from(geo_types::Rect::from(rect)), Ok(rect));
+    }
+}
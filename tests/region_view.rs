@@ -0,0 +1,81 @@
+use image::{GenericImageView, Rgba, RgbaImage};
+use wplace_core_library::{
+    canvas_rect::CanvasRect, global_pixel::GlobalPixel, region_view::RegionView,
+    tile_coords::TileCoords, tile_downloader::Tile, tile_source::MemoryTileSource,
+};
+
+/// Which tile a pixel comes from and where in it
+fn expected_pixel(pixel: GlobalPixel) -> Rgba<u8> {
+    let tile = pixel.get_tile();
+    Rgba([
+        tile.get_x() as u8,
+        tile.get_y() as u8,
+        pixel.get_x_in_tile() as u8,
+        pixel.get_y_in_tile() as u8,
+    ])
+}
+
+fn pixel(tile_x: u16, tile_y: u16, x: u16, y: u16) -> GlobalPixel {
+    GlobalPixel::try_from(TileCoords::new(tile_x, tile_y, x, y)).unwrap()
+}
+
+/// 4 tiles wide going around the antimeridian, and 2 tiles high
+async fn region() -> RegionView {
+    let source = itertools::iproduct!([2046, 2047, 0, 1], [10, 11])
+        .map(|(x, y)| {
+            let image = RgbaImage::from_fn(1000, 1000, |px, py| {
+                expected_pixel(pixel(x, y, px as u16, py as u16))
+            });
+            (Tile::new(x, y), image)
+        })
+        .collect::<MemoryTileSource>();
+    let rect = CanvasRect::new(pixel(2046, 10, 990, 995), 2030, 10).unwrap();
+    RegionView::from_site_rect(&source, &rect).await.unwrap()
+}
+
+#[tokio::test]
+async fn reads_global_pixels_on_both_sides_of_every_seam() {
+    let region = region().await;
+    for (left, right) in [
+        (pixel(2046, 10, 999, 995), pixel(2047, 10, 0, 995)),
+        (pixel(2047, 10, 999, 999), pixel(0, 10, 0, 999)),
+        (pixel(0, 11, 999, 0), pixel(1, 11, 0, 0)),
+        (pixel(1, 10, 19, 999), pixel(1, 11, 19, 0)),
+        (pixel(2046, 10, 990, 999), pixel(2046, 11, 990, 0)),
+    ] {
+        assert_eq!(region.get_global_pixel(left), Some(expected_pixel(left)));
+        assert_eq!(region.get_global_pixel(right), Some(expected_pixel(right)));
+    }
+
+    for outside in [
+        pixel(2046, 10, 989, 995),
+        pixel(1, 10, 20, 995),
+        pixel(0, 10, 0, 994),
+        pixel(0, 11, 0, 5),
+        pixel(1000, 10, 0, 995),
+    ] {
+        assert_eq!(region.get_global_pixel(outside), None, "{outside:?}");
+    }
+}
+
+#[tokio::test]
+async fn splits_rows_at_every_tile() {
+    let region = region().await;
+    for y in 0..region.get_height() {
+        let lengths = region
+            .get_row_slices(y)
+            .map(|v| v.len() / 4)
+            .collect::<Vec<_>>();
+        assert_eq!(lengths, [10, 1000, 1000, 20], "Row {y}");
+
+        let first = region.get_top_left_corner().wrapping_add(0, y);
+        let pixels = region.get_row_pixels(y).collect::<Vec<_>>();
+        assert_eq!(pixels.len(), 2030);
+        for (x, &pixel) in pixels.iter().enumerate() {
+            let global = first.wrapping_add(x as u32, 0);
+            assert_eq!(pixel, expected_pixel(global), "{x}, {y}");
+            assert_eq!(region.get_global_pixel(global), Some(pixel));
+            assert_eq!(region.get_pixel(x as u32, y), pixel);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use image::{Rgba, RgbaImage};
+use serde_json::json;
+use wplace_core_library::{
+    color::Color, image_comparison::ImageComparison, image_data::ImageData, map_coords::MapCoords,
+    nominatim_data::NominatimData, tile_coords::TileCoords,
+};
+
+#[test]
+fn coordinates_and_colors_round_trip() {
+    let coords = TileCoords::new(1, 2, 3, 4);
+    let value = serde_json::to_value(coords).unwrap();
+    assert_eq!(value, json!({"tile_x": 1, "tile_y": 2, "x": 3, "y": 4}));
+    assert_eq!(serde_json::from_value::<TileCoords>(value).unwrap(), coords);
+
+    let map_coords = MapCoords::new(45.5, -12.25, 11.0);
+    let value = serde_json::to_value(&map_coords).unwrap();
+    assert_eq!(value, json!({"lat": 45.5, "lng": -12.25, "zoom": 11.0}));
+    assert_eq!(
+        serde_json::from_value::<MapCoords>(value).unwrap(),
+        map_coords
+    );
+
+    assert_eq!(
+        serde_json::to_value(Color::DarkGray).unwrap(),
+        json!("DarkGray")
+    );
+    assert_eq!(
+        serde_json::from_value::<Color>(json!("LightSlate")).unwrap(),
+        Color::LightSlate
+    );
+
+    let nominatim: NominatimData =
+        serde_json::from_value(json!({"display_name": "Somewhere"})).unwrap();
+    assert_eq!(nominatim.get_display_name(), "Somewhere");
+    assert_eq!(
+        serde_json::to_value(&nominatim).unwrap(),
+        json!({"display_name": "Somewhere"})
+    );
+}
+
+#[test]
+fn comparisons_round_trip_without_the_image() {
+    let template = ImageData::new(RgbaImage::from_pixel(3, 2, Rgba(Color::Red.into()))).unwrap();
+    let mut current = RgbaImage::from_pixel(3, 2, Rgba(Color::Red.into()));
+    current.put_pixel(1, 0, Rgba(Color::Blue.into()));
+    current.put_pixel(2, 1, Rgba(Color::Blue.into()));
+    let current = ImageData::new(current).unwrap();
+
+    let comparison = ImageComparison::compare_images(&template, &current).unwrap();
+    let value = serde_json::to_value(&comparison).unwrap();
+    assert_eq!(
+        value,
+        json!({
+            "width": 3,
+            "height": 2,
+            "different_px": [[1, 0], [2, 1]],
+            "difference_color_count": {"Red": 2},
+            "total_different_px": 2,
+        })
+    );
+
+    let deserialized: ImageComparison = serde_json::from_value(value).unwrap();
+    assert_eq!(
+        deserialized.get_difference_image(),
+        comparison.get_difference_image()
+    );
+    assert_eq!(
+        deserialized.get_different_px(),
+        comparison.get_different_px()
+    );
+    assert_eq!(
+        deserialized.get_difference_color_count(),
+        comparison.get_difference_color_count()
+    );
+
+    assert!(
+        serde_json::from_value::<ImageComparison>(json!({
+            "width": 3,
+            "height": 2,
+            "different_px": [[3, 0]],
+            "difference_color_count": {},
+        }))
+        .is_err()
+    );
+}
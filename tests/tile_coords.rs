@@ -0,0 +1,312 @@
+use wplace_core_library::{
+    map_coords::MapCoords,
+    tile_coords::{TileCoords, TileCoordsError, TileCoordsFormat},
+};
+
+fn parse(v: &str) -> Result<TileCoords, TileCoordsError> {
+    TileCoords::parse_tile_coords_string(v)
+}
+
+#[test]
+fn parses_what_display_writes() {
+    let mut rng = fastrand::Rng::with_seed(17);
+    let edges = [
+        TileCoords::new(0, 0, 0, 0),
+        TileCoords::new(2047, 2047, 999, 999),
+    ];
+    let random = (0..1000).map(|_| {
+        TileCoords::new(
+            rng.u16(0..2048),
+            rng.u16(0..2048),
+            rng.u16(0..1000),
+            rng.u16(0..1000),
+        )
+    });
+
+    for coords in edges.into_iter().chain(random) {
+        assert_eq!(parse(&coords.to_string()), Ok(coords));
+        assert_eq!(coords.to_string().parse(), Ok(coords));
+    }
+}
+
+#[test]
+fn accepts_fields_in_any_order_and_any_whitespace() {
+    let expected = Ok(TileCoords::new(1, 2, 3, 4));
+    for v in [
+        "(Px Y: 4, Tl Y: 2, Px X: 3, Tl X: 1)",
+        "  px y:4,TL X : 1 ,\tTl Y:2 ,\n Px X:3  ",
+        "(Tl X: 1, Tl Y: 2, Px X: 3, Px Y: 4,)",
+        "( Tl X:1,Tl Y:2,Px X:3,Px Y:4 )",
+    ] {
+        assert_eq!(parse(v), expected, "{v}");
+    }
+}
+
+#[test]
+fn rejects_duplicate_and_missing_fields() {
+    assert_eq!(
+        parse("Tl X: 1, Tl X: 2, Tl Y: 2, Px X: 3, Px Y: 4"),
+        Err(TileCoordsError::DuplicateField {
+            offset: 9,
+            field: "Tl X"
+        })
+    );
+    // Used to hit an `unreachable!()`
+    assert_eq!(
+        parse("(Tl X: 1, Tl Y: 2, Px X: 3, Px Y: 4, Px X: 5)"),
+        Err(TileCoordsError::DuplicateField {
+            offset: 37,
+            field: "Px X"
+        })
+    );
+    assert_eq!(
+        parse("(Tl X: 1, Tl Y: 2, Px Y: 4)"),
+        Err(TileCoordsError::MissingField { field: "Px X" })
+    );
+}
+
+#[test]
+fn reports_where_values_are_out_of_range() {
+    assert_eq!(
+        parse("(Tl X: 2048, Tl Y: 2, Px X: 3, Px Y: 4)"),
+        Err(TileCoordsError::OutOfRange {
+            offset: 7,
+            field: "Tl X",
+            value: 2048,
+            max: 2047
+        })
+    );
+    assert_eq!(
+        parse("(Tl X: 1, Tl Y: 2, Px X: 1000, Px Y: 4)"),
+        Err(TileCoordsError::OutOfRange {
+            offset: 25,
+            field: "Px X",
+            value: 1000,
+            max: 999
+        })
+    );
+    assert_eq!(
+        parse("(Tl X: 99999999999999999999999, Tl Y: 2, Px X: 3, Px Y: 4)"),
+        Err(TileCoordsError::OutOfRange {
+            offset: 7,
+            field: "Tl X",
+            value: u64::MAX,
+            max: 2047
+        })
+    );
+}
+
+#[test]
+fn reports_where_unexpected_tokens_are() {
+    for (v, offset, expected) in [
+        ("", 0, "`Tl X`, `Tl Y`, `Px X` or `Px Y`"),
+        ("(Foo: 1)", 1, "`Tl X`, `Tl Y`, `Px X` or `Px Y`"),
+        ("(Tl Z: 1)", 1, "`Tl X`, `Tl Y`, `Px X` or `Px Y`"),
+        ("Tl X 1", 5, "`:`"),
+        ("Tl X: -1", 6, "a number"),
+        ("(Tl X: 1, Tl Y: 2, Px X: 3, Px Y: 4", 35, "`,` or `)`"),
+        (
+            "(Tl X: 1, Tl Y: 2, Px X: 3, Px Y: 4) foo",
+            37,
+            "the end of the input",
+        ),
+        (
+            "Tl X: 1, Tl Y: 2, Px X: 3, Px Y: 4 foo",
+            35,
+            "`,` or the end of the input",
+        ),
+    ] {
+        assert_eq!(
+            parse(v),
+            Err(TileCoordsError::UnexpectedToken { offset, expected }),
+            "{v}"
+        );
+    }
+}
+
+#[test]
+fn detects_formats() {
+    for (v, format) in [
+        (
+            "(Tl X: 1, Tl Y: 2, Px X: 3, Px Y: 4)",
+            TileCoordsFormat::Labeled,
+        ),
+        (
+            "Chunk X: 1, Chunk Y: 2, Pos X: 3, Pos Y: 4",
+            TileCoordsFormat::Labeled,
+        ),
+        ("1, 2, 3, 4", TileCoordsFormat::Tuple),
+        (" ( 1, 2, 3, 4)", TileCoordsFormat::Tuple),
+        ("[1 2 3 4]", TileCoordsFormat::Tuple),
+        (
+            "https://wplace.live/?lat=41.9&lng=12.5&zoom=14",
+            TileCoordsFormat::ShareLink,
+        ),
+        (
+            "https://backend.wplace.live/s0/pixel/1094/759?x=12&y=999",
+            TileCoordsFormat::PixelUrl,
+        ),
+    ] {
+        assert_eq!(TileCoords::detect_format(v), format, "{v}");
+    }
+}
+
+#[test]
+fn parses_userscript_labels() {
+    let expected = Ok(TileCoords::new(1, 2, 3, 4));
+    for v in [
+        "(Tile X: 1, Tile Y: 2, Pixel X: 3, Pixel Y: 4)",
+        "Chunk X: 1, Chunk Y: 2, Pos X: 3, Pos Y: 4",
+        "(Pixel Y: 4, chunk x: 1, TILE Y: 2, pos X: 3)",
+    ] {
+        assert_eq!(v.parse(), expected, "{v}");
+    }
+}
+
+#[test]
+fn parses_tuples() {
+    let expected = Ok(TileCoords::new(1094, 759, 12, 999));
+    for v in [
+        "1094, 759, 12, 999",
+        "(1094,759,12,999)",
+        "[1094 759 12 999]",
+        "  [ 1094 ,759\t12, 999 ]  ",
+    ] {
+        assert_eq!(v.parse(), expected, "{v}");
+    }
+
+    for (v, error) in [
+        (
+            "[1094, 759, 12, 999)",
+            TileCoordsError::UnexpectedToken {
+                offset: 19,
+                expected: "`]`",
+            },
+        ),
+        (
+            "1094, 759, 12",
+            TileCoordsError::UnexpectedToken {
+                offset: 13,
+                expected: "`,` or whitespace",
+            },
+        ),
+        (
+            "1094, 759, 12, 999, 5",
+            TileCoordsError::UnexpectedToken {
+                offset: 18,
+                expected: "the end of the input",
+            },
+        ),
+        (
+            "1094, 759, 1000, 999",
+            TileCoordsError::OutOfRange {
+                offset: 11,
+                field: "Px X",
+                value: 1000,
+                max: 999,
+            },
+        ),
+    ] {
+        assert_eq!(v.parse::<TileCoords>(), Err(error), "{v}");
+    }
+}
+
+#[test]
+fn parses_share_links_back() {
+    let mut rng = fastrand::Rng::with_seed(18);
+    for _ in 0..1000 {
+        let coords = TileCoords::new(
+            rng.u16(0..2048),
+            rng.u16(0..2048),
+            rng.u16(0..1000),
+            rng.u16(0..1000),
+        );
+        let link = MapCoords::from_tile_coords(&coords, 1, 1).get_link();
+        assert_eq!(link.parse(), Ok(coords), "{link}");
+    }
+
+    assert_eq!(
+        "https://wplace.live/?zoom=3&lon=0&lat=0".parse(),
+        Ok(TileCoords::new(1024, 1024, 0, 0))
+    );
+    assert_eq!(
+        "https://wplace.live/?lat=abc&lng=12.5".parse::<TileCoords>(),
+        Err(TileCoordsError::UnexpectedToken {
+            offset: 25,
+            expected: "a number",
+        })
+    );
+    assert_eq!(
+        "https://wplace.live/?lat=41.9".parse::<TileCoords>(),
+        Err(TileCoordsError::MissingField { field: "lng" })
+    );
+    assert_eq!(
+        "https://wplace.live/?lat=89&lng=0".parse::<TileCoords>(),
+        Err(TileCoordsError::OutOfCanvas {
+            lat: 89.0,
+            lng: 0.0
+        })
+    );
+}
+
+#[test]
+fn parses_pixel_urls() {
+    assert_eq!(
+        "https://backend.wplace.live/s0/pixel/1094/759?x=12&y=999".parse(),
+        Ok(TileCoords::new(1094, 759, 12, 999))
+    );
+    assert_eq!(
+        "https://backend.wplace.live/s0/pixel/1094/759?y=999&x=12".parse(),
+        Ok(TileCoords::new(1094, 759, 12, 999))
+    );
+    assert_eq!(
+        "https://backend.wplace.live/s0/pixel/1/2?season=0&x=3&y=4#top".parse(),
+        Ok(TileCoords::new(1, 2, 3, 4))
+    );
+    for (v, offset, expected) in [
+        (
+            "https://backend.wplace.live/s0/pixel/1/2?x=3abc&y=4",
+            44,
+            "`&`, `#` or the end of the input",
+        ),
+        (
+            "https://backend.wplace.live/s0/pixel/1/2?x=3&y=4z",
+            48,
+            "`&`, `#` or the end of the input",
+        ),
+        (
+            "https://backend.wplace.live/s0/pixel/1/2/junk?x=3&y=4",
+            40,
+            "`?`",
+        ),
+        ("https://backend.wplace.live/s0/pixel/1/2", 40, "`?`"),
+    ] {
+        assert_eq!(
+            v.parse::<TileCoords>(),
+            Err(TileCoordsError::UnexpectedToken { offset, expected }),
+            "{v}"
+        );
+    }
+    assert_eq!(
+        "https://backend.wplace.live/s0/pixel/1094/759?x=12".parse::<TileCoords>(),
+        Err(TileCoordsError::MissingField { field: "Px Y" })
+    );
+    assert_eq!(
+        "https://backend.wplace.live/s0/pixel/1094/759?x=1000&y=999".parse::<TileCoords>(),
+        Err(TileCoordsError::OutOfRange {
+            offset: 48,
+            field: "Px X",
+            value: 1000,
+            max: 999,
+        })
+    );
+    assert_eq!(
+        "https://backend.wplace.live/s0/pixel/2048/759?x=12&y=999".parse::<TileCoords>(),
+        Err(TileCoordsError::OutOfRange {
+            offset: 37,
+            field: "Tl X",
+            value: 2048,
+            max: 2047,
+        })
+    );
+}